@@ -1,28 +1,37 @@
 pub(crate) mod error;
+pub mod rollback;
 
 use anyhow::{Result, anyhow};
 use chrono::NaiveDateTime;
-use libsql::{Builder, Row, Rows, params, params::IntoParams};
+use libsql::{Builder, Connection, Row, Rows, params, params::IntoParams};
+use std::sync::Arc;
+use std::time::Duration;
 
 use crate::configuration::database::Configuration;
+use rollback::Rollback;
+use runtime::kv::KeyValueStore;
 use runtime::proxy::{Proxy, ProxyMetadata};
 
+/// How long a statement waits for a lock before failing with `SQLITE_BUSY`.
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Clone)]
 pub struct Database {
-    handle: libsql::Database,
+    handle: Arc<libsql::Database>,
 }
 
 impl Database {
     pub async fn new(configuration: &Configuration) -> Result<Self> {
         let Configuration { name, path } = configuration;
         let path = format!("{}/{}.sqlite", path, name);
-        let handle = Builder::new_local(path).build().await?;
+        let handle = Arc::new(Builder::new_local(path).build().await?);
         let database = Database { handle };
         database.init().await?;
         Ok(database)
     }
 
     async fn init(&self) -> Result<()> {
-        let connection = self.handle.connect()?;
+        let connection = self.connect()?;
         let statements = [
             r#"
             CREATE TABLE IF NOT EXISTS proxies (
@@ -50,6 +59,38 @@ impl Database {
             FROM   proxies p
             JOIN   current_proxy s ON p.tag = s.selected_tag
             WHERE  s.singleton = 1;"#,
+            r#"CREATE TABLE IF NOT EXISTS proxy_configurations (
+                tag TEXT PRIMARY KEY,
+                document TEXT NOT NULL,
+                FOREIGN KEY (tag) REFERENCES proxies(tag)
+                    ON DELETE CASCADE
+                    ON UPDATE CASCADE
+            );"#,
+            r#"CREATE TABLE IF NOT EXISTS kv (
+                namespace TEXT NOT NULL,
+                key TEXT NOT NULL,
+                value BLOB NOT NULL,
+                PRIMARY KEY (namespace, key)
+            );"#,
+            r#"CREATE TABLE IF NOT EXISTS chain (
+                position INTEGER PRIMARY KEY,
+                tag TEXT NOT NULL,
+                FOREIGN KEY (tag) REFERENCES proxies(tag)
+                    ON DELETE CASCADE
+                    ON UPDATE CASCADE
+            );"#,
+            r#"CREATE TABLE IF NOT EXISTS proxy_history (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                tag TEXT NOT NULL,
+                activated_at TEXT NOT NULL DEFAULT current_timestamp
+            );"#,
+            r#"CREATE TABLE IF NOT EXISTS rollbacks (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                from_tag TEXT NOT NULL,
+                to_tag TEXT,
+                reason TEXT NOT NULL,
+                created_at TEXT NOT NULL DEFAULT current_timestamp
+            );"#,
         ];
         for statement in statements {
             connection.execute(statement, ()).await?;
@@ -57,8 +98,16 @@ impl Database {
         Ok(())
     }
 
-    async fn query(&self, statement: &str, params: impl IntoParams) -> Result<Rows> {
+    /// Opens a connection that waits for locks held by concurrent writers,
+    /// guests share the database through the `kv` interface.
+    fn connect(&self) -> Result<Connection> {
         let connection = self.handle.connect()?;
+        connection.busy_timeout(BUSY_TIMEOUT)?;
+        Ok(connection)
+    }
+
+    async fn query(&self, statement: &str, params: impl IntoParams) -> Result<Rows> {
+        let connection = self.connect()?;
         connection
             .query(statement, params)
             .await
@@ -78,9 +127,76 @@ impl Database {
         }
         let statement = r#"UPDATE current_proxy SET selected_tag = ? WHERE singleton = 1;"#;
         let _ = self.query(statement, params!(tag)).await?;
+        let statement = "INSERT INTO proxy_history (tag) VALUES (?);";
+        let _ = self.query(statement, params!(tag)).await?;
         self.get_proxy(tag).await
     }
 
+    /// Takes `tag` out of service. A member of the chain is removed from it,
+    /// without a chain the current proxy is replaced with the most recently
+    /// selected one that still exists, `tag` is not considered as a target
+    /// again. Returns `None` if `tag` is not serving requests.
+    pub async fn rollback_proxy(&self, tag: &str, reason: &str) -> Result<Option<Rollback>> {
+        let chain = self.get_chain().await?;
+        let to_tag = if chain.is_empty() {
+            let is_current = self
+                .get_current_proxy()
+                .await?
+                .is_some_and(|current| current.tag == tag);
+            if !is_current {
+                return Ok(None);
+            }
+            self.roll_back_current_proxy(tag).await?
+        } else {
+            if !chain.iter().any(|proxy_metadata| proxy_metadata.tag == tag) {
+                return Ok(None);
+            }
+            let statement = "DELETE FROM chain WHERE tag = ?;";
+            let _ = self.query(statement, params![tag]).await?;
+            None
+        };
+        let statement = r#"INSERT INTO rollbacks (from_tag, to_tag, reason) VALUES (?, ?, ?)
+            RETURNING from_tag, to_tag, reason, created_at;"#;
+        let mut rows = self.query(statement, params![tag, to_tag, reason]).await?;
+        match rows.next().await? {
+            Some(row) => Ok(Some(Self::try_row_to_rollback(&row)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Selects the most recently selected proxy other than `tag` that still
+    /// exists, if any.
+    async fn roll_back_current_proxy(&self, tag: &str) -> Result<Option<String>> {
+        let statement = "DELETE FROM proxy_history WHERE tag = ?;";
+        let _ = self.query(statement, params![tag]).await?;
+        let statement = r#"SELECT h.tag FROM proxy_history h
+            JOIN proxies p ON p.tag = h.tag
+            ORDER BY h.id DESC
+            LIMIT 1;"#;
+        let mut rows = self.query(statement, ()).await?;
+        let to_tag = match rows.next().await? {
+            Some(row) => Some(row.get::<String>(0)?),
+            None => None,
+        };
+        // The open statement would keep the write below waiting for its lock.
+        drop(rows);
+        let statement = r#"UPDATE current_proxy SET selected_tag = ? WHERE singleton = 1;"#;
+        let _ = self.query(statement, params![to_tag.clone()]).await?;
+        Ok(to_tag)
+    }
+
+    pub async fn rollbacks(&self) -> Result<Vec<Rollback>> {
+        let statement = r#"SELECT from_tag, to_tag, reason, created_at
+            FROM rollbacks
+            ORDER BY id DESC;"#;
+        let mut rows = self.query(statement, ()).await?;
+        let mut result = Vec::new();
+        while let Some(row) = rows.next().await? {
+            result.push(Self::try_row_to_rollback(&row)?);
+        }
+        Ok(result)
+    }
+
     pub async fn all_proxies(&self) -> Result<Vec<ProxyMetadata>> {
         let statement = "SELECT tag, created_at, updated_at FROM proxies;";
         let mut rows = self.query(statement, ()).await?;
@@ -116,6 +232,10 @@ impl Database {
     }
 
     pub async fn delete_proxy(&self, tag: String) -> Result<Option<ProxyMetadata>> {
+        let statement = "DELETE FROM proxy_configurations WHERE tag = ?;";
+        let _ = self.query(statement, params![tag.clone()]).await?;
+        let statement = "DELETE FROM chain WHERE tag = ?;";
+        let _ = self.query(statement, params![tag.clone()]).await?;
         let statement = "DELETE FROM proxies WHERE tag = ? RETURNING *;";
         let mut rows = self.query(statement, params![tag]).await?;
         Self::try_to_proxy_metadata(&mut rows).await
@@ -128,11 +248,144 @@ impl Database {
     }
 
     pub async fn get_proxy(&self, tag: &str) -> Result<Option<Proxy>> {
-        let statement = "SELECT tag, created_at, updated_at, component FROM proxies WHERE tag = ?;";
+        let statement = r#"SELECT p.tag, p.created_at, p.updated_at, p.component, c.document
+            FROM proxies p
+            LEFT JOIN proxy_configurations c ON p.tag = c.tag
+            WHERE p.tag = ?;"#;
         let mut rows = self.query(statement, params![tag]).await?;
         Self::try_to_proxy(&mut rows).await
     }
 
+    pub async fn get_proxy_configuration(&self, tag: &str) -> Result<Option<serde_json::Value>> {
+        let statement = "SELECT document FROM proxy_configurations WHERE tag = ?;";
+        let mut rows = self.query(statement, params![tag]).await?;
+        match rows.next().await? {
+            Some(row) => Ok(Some(serde_json::from_str(&row.get::<String>(0)?)?)),
+            None => Ok(None),
+        }
+    }
+
+    pub async fn set_proxy_configuration(
+        &self,
+        tag: &str,
+        document: &serde_json::Value,
+    ) -> Result<Option<ProxyMetadata>> {
+        let Some(proxy_metadata) = self.proxy_exists(tag).await? else {
+            return Ok(None);
+        };
+        let statement = r#"INSERT INTO proxy_configurations (tag, document) VALUES (?, ?)
+            ON CONFLICT (tag) DO UPDATE SET document = excluded.document;"#;
+        let document = serde_json::to_string(document)?;
+        let _ = self.query(statement, params![tag, document]).await?;
+        Ok(Some(proxy_metadata))
+    }
+
+    pub async fn get_chain(&self) -> Result<Vec<ProxyMetadata>> {
+        let statement = r#"SELECT p.tag, p.created_at, p.updated_at
+            FROM chain c
+            JOIN proxies p ON p.tag = c.tag
+            ORDER BY c.position;"#;
+        let mut rows = self.query(statement, ()).await?;
+        let mut result = Vec::new();
+        while let Some(row) = rows.next().await? {
+            result.push(Self::try_row_to_proxy_metadata(&row).await?);
+        }
+        Ok(result)
+    }
+
+    /// Replaces the chain at once, returns `None` without changes if one of the
+    /// tags does not exist.
+    pub async fn set_chain(&self, tags: &[String]) -> Result<Option<Vec<ProxyMetadata>>> {
+        for tag in tags {
+            if self.proxy_exists(tag).await?.is_none() {
+                return Ok(None);
+            }
+        }
+        let connection = self.connect()?;
+        let transaction = connection.transaction().await?;
+        transaction.execute("DELETE FROM chain;", ()).await?;
+        let statement = "INSERT INTO chain (position, tag) VALUES (?, ?);";
+        for (position, tag) in tags.iter().enumerate() {
+            transaction
+                .execute(statement, params![position as i64, tag.clone()])
+                .await?;
+        }
+        transaction.commit().await?;
+        self.get_chain().await.map(Some)
+    }
+
+    /// Proxies serving requests, the chain if one is set and otherwise the
+    /// current proxy.
+    pub async fn get_active_proxies(&self) -> Result<Vec<Proxy>> {
+        let mut proxies = Vec::new();
+        for tag in self.active_tags().await? {
+            let proxy = self
+                .get_proxy(&tag)
+                .await?
+                .ok_or_else(|| anyhow!("Proxy {} does not exist", tag))?;
+            proxies.push(proxy);
+        }
+        Ok(proxies)
+    }
+
+    pub async fn is_active_proxy(&self, tag: &str) -> Result<bool> {
+        Ok(self.active_tags().await?.iter().any(|active| active == tag))
+    }
+
+    async fn active_tags(&self) -> Result<Vec<String>> {
+        let chain = self.get_chain().await?;
+        if chain.is_empty() {
+            let current = self.get_current_proxy().await?;
+            return Ok(current.into_iter().map(|current| current.tag).collect());
+        }
+        Ok(chain
+            .into_iter()
+            .map(|proxy_metadata| proxy_metadata.tag)
+            .collect())
+    }
+
+    pub async fn kv_get(&self, namespace: &str, key: &str) -> Result<Option<Vec<u8>>> {
+        let statement = "SELECT CAST(value AS BLOB) FROM kv WHERE namespace = ? AND key = ?;";
+        let mut rows = self.query(statement, params![namespace, key]).await?;
+        match rows.next().await? {
+            Some(row) => Ok(Some(row.get::<Vec<u8>>(0)?)),
+            None => Ok(None),
+        }
+    }
+
+    pub async fn kv_set(&self, namespace: &str, key: &str, value: Vec<u8>) -> Result<()> {
+        let statement = r#"INSERT INTO kv (namespace, key, value) VALUES (?, ?, ?)
+            ON CONFLICT (namespace, key) DO UPDATE SET value = excluded.value;"#;
+        let _ = self
+            .query(statement, params![namespace, key, value])
+            .await?;
+        Ok(())
+    }
+
+    pub async fn kv_delete(&self, namespace: &str, key: &str) -> Result<()> {
+        let statement = "DELETE FROM kv WHERE namespace = ? AND key = ?;";
+        let _ = self.query(statement, params![namespace, key]).await?;
+        Ok(())
+    }
+
+    /// Adds `delta` to the integer stored at `key`, starting from 0. A value
+    /// that is not an integer is left as it is and fails the increment.
+    pub async fn kv_increment(&self, namespace: &str, key: &str, delta: i64) -> Result<i64> {
+        let statement = r#"INSERT INTO kv (namespace, key, value) VALUES (?1, ?2, CAST(?3 AS TEXT))
+            ON CONFLICT (namespace, key)
+            DO UPDATE SET value = CAST(CAST(value AS INTEGER) + ?3 AS TEXT)
+            WHERE CAST(value AS TEXT) = CAST(CAST(value AS INTEGER) AS TEXT)
+            RETURNING CAST(value AS INTEGER);"#;
+        let mut rows = self
+            .query(statement, params![namespace, key, delta])
+            .await?;
+        let row = rows
+            .next()
+            .await?
+            .ok_or_else(|| anyhow!("Value of {} is not an integer", key))?;
+        Ok(row.get::<i64>(0)?)
+    }
+
     async fn try_to_proxy_metadata(rows: &mut Rows) -> Result<Option<ProxyMetadata>> {
         if let Some(row) = rows.next().await? {
             let proxy_metdata = Self::try_row_to_proxy_metadata(&row).await?;
@@ -158,6 +411,18 @@ impl Database {
         Ok(proxy_metadata)
     }
 
+    fn try_row_to_rollback(row: &Row) -> Result<Rollback> {
+        let date_as_string = row.get::<String>(3)?;
+        let native_date = NaiveDateTime::parse_from_str(&date_as_string, "%Y-%m-%d %H:%M:%S")?;
+        let rollback = Rollback {
+            from_tag: row.get::<String>(0)?,
+            to_tag: row.get::<Option<String>>(1)?,
+            reason: row.get::<String>(2)?,
+            created_at: native_date.and_utc().timestamp(),
+        };
+        Ok(rollback)
+    }
+
     async fn try_to_proxy(rows: &mut Rows) -> Result<Option<Proxy>> {
         if let Some(row) = rows.next().await? {
             let proxy = Self::try_row_to_proxy(&row).await?;
@@ -176,6 +441,10 @@ impl Database {
         let native_date = NaiveDateTime::parse_from_str(&date_as_string, "%Y-%m-%d %H:%M:%S")?;
         let updated_at = native_date.and_utc().timestamp();
         let component = row.get::<Vec<u8>>(3)?;
+        let configuration = row
+            .get::<Option<String>>(4)?
+            .map(|document| serde_json::from_str(&document))
+            .transpose()?;
         let proxy = Proxy {
             metadata: ProxyMetadata {
                 tag,
@@ -183,7 +452,27 @@ impl Database {
                 updated_at,
             },
             component,
+            configuration,
         };
         Ok(proxy)
     }
 }
+
+#[async_trait::async_trait]
+impl KeyValueStore for Database {
+    async fn get(&self, namespace: &str, key: &str) -> Result<Option<Vec<u8>>> {
+        self.kv_get(namespace, key).await
+    }
+
+    async fn set(&self, namespace: &str, key: &str, value: Vec<u8>) -> Result<()> {
+        self.kv_set(namespace, key, value).await
+    }
+
+    async fn delete(&self, namespace: &str, key: &str) -> Result<()> {
+        self.kv_delete(namespace, key).await
+    }
+
+    async fn increment(&self, namespace: &str, key: &str, delta: i64) -> Result<i64> {
+        self.kv_increment(namespace, key, delta).await
+    }
+}
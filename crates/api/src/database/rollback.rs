@@ -0,0 +1,10 @@
+/// A proxy replaced automatically after it trapped too often.
+#[derive(Debug, serde::Serialize)]
+pub struct Rollback {
+    pub from_tag: String,
+    /// `None` if the proxy was removed from the chain, or no earlier proxy was
+    /// left and the default took over.
+    pub to_tag: Option<String>,
+    pub reason: String,
+    pub created_at: i64,
+}
@@ -2,17 +2,18 @@ mod loader;
 
 use anyhow::Result;
 use axum::extract::{Path, State};
-use axum::http::StatusCode;
+use axum::http::{HeaderMap, StatusCode, header};
 use axum::response::Json;
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
 use crate::database::Database;
 use crate::database::error::Error as DbErr;
+use crate::database::rollback::Rollback;
 use crate::error::Error as ApiErr;
 use loader::Loader;
-use runtime::Runtime;
-use runtime::proxy::ProxyMetadata;
+use runtime::proxy::{Proxy, ProxyMetadata};
+use runtime::{Runtime, Stats};
 
 pub(super) async fn current_proxy(
     State((db, _)): State<(Arc<RwLock<Database>>, Runtime)>,
@@ -30,17 +31,26 @@ pub(super) async fn set_current_proxy(
     Path(tag): Path<String>,
 ) -> Result<StatusCode, (StatusCode, Json<serde_json::Value>)> {
     let db = db.write().await;
-    let maybe_proxy_metadata = db
-        .set_current_proxy(&tag)
+    // The current proxy only serves requests without a chain.
+    let chain = db
+        .get_chain()
         .await
         .map_err(|_| ApiErr::DatabaseError(DbErr::UnableToReadRoads))?;
-    let Some(proxy) = maybe_proxy_metadata else {
+    if !chain.is_empty() {
+        return Err(ApiErr::ChainIsSet.into());
+    }
+    let Some(proxy) = db
+        .get_proxy(&tag)
+        .await
+        .map_err(|_| ApiErr::DatabaseError(DbErr::UnableToReadRoads))?
+    else {
         return Ok(StatusCode::NOT_FOUND);
     };
-
-    runtime
-        .set_proxy(&proxy.component)
-        .map_err(|_| ApiErr::DatabaseError(DbErr::UnableToReadRoads))?;
+    load_chain(&runtime, vec![proxy]).await?;
+    if db.set_current_proxy(&tag).await.is_err() {
+        let error = ApiErr::DatabaseError(DbErr::UnableToUpdateRoad);
+        return Err(restore(&db, &runtime, error).await.into());
+    }
     Ok(StatusCode::OK)
 }
 
@@ -56,12 +66,13 @@ pub(super) async fn all_proxies(
 }
 
 pub(super) async fn create_proxy(
-    State((db, _)): State<(Arc<RwLock<Database>>, Runtime)>,
+    State((db, runtime)): State<(Arc<RwLock<Database>>, Runtime)>,
     Path(tag): Path<String>,
     Json(loader): Json<Loader>,
 ) -> Result<StatusCode, (StatusCode, Json<serde_json::Value>)> {
-    let db = db.write().await;
     let component = loader.load().map_err(|e| ApiErr::FailedToLoad(e))?;
+    let component = validate(&runtime, component).await?;
+    let db = db.write().await;
     let Some(_tag) = db
         .create_proxy(tag, component.clone())
         .await
@@ -89,58 +100,286 @@ pub(super) async fn update_proxy(
     Path(tag): Path<String>,
     Json(loader): Json<Loader>,
 ) -> Result<StatusCode, (StatusCode, Json<serde_json::Value>)> {
-    let db = db.write().await;
     let component = loader.load().map_err(|e| ApiErr::FailedToLoad(e))?;
-    let Some(proxy_metadata) = db
-        .update_proxy(tag, component.clone())
+    let component = validate(&runtime, component).await?;
+    let db = db.write().await;
+    let previous = db
+        .get_proxy(&tag)
         .await
-        .map_err(|_| ApiErr::DatabaseError(DbErr::UnableToUpdateRoad))?
-    else {
+        .map_err(|_| ApiErr::DatabaseError(DbErr::UnableToReadRoads))?;
+    let Some(previous) = previous else {
         return Ok(StatusCode::NOT_FOUND);
     };
-    let current_proxy_metadata = db
-        .get_current_proxy()
+    load_changed(&db, &runtime, &tag, |proxy| {
+        proxy.component = component.clone()
+    })
+    .await?;
+    let stored = db.update_proxy(tag, component.clone()).await;
+    // Loading the component cached it, only the stored one keeps its artifact.
+    if previous.component != component {
+        match &stored {
+            Ok(_) => runtime.forget(&previous.component),
+            Err(_) => runtime.forget(&component),
+        }
+    }
+    match stored {
+        Ok(_) => Ok(StatusCode::OK),
+        Err(_) => {
+            let error = ApiErr::DatabaseError(DbErr::UnableToUpdateRoad);
+            Err(restore(&db, &runtime, error).await.into())
+        }
+    }
+}
+
+pub(super) async fn delete_proxy(
+    State((db, runtime)): State<(Arc<RwLock<Database>>, Runtime)>,
+    Path(tag): Path<String>,
+) -> Result<StatusCode, (StatusCode, Json<serde_json::Value>)> {
+    let db = db.write().await;
+    let is_active = db
+        .is_active_proxy(&tag)
+        .await
+        .map_err(|_| ApiErr::DatabaseError(DbErr::UnableToReadRoads))?;
+    let Some(proxy) = db
+        .get_proxy(&tag)
         .await
         .map_err(|_| ApiErr::DatabaseError(DbErr::UnableToReadRoads))?
-        .ok_or(ApiErr::DatabaseError(DbErr::UnableToReadRoads))?;
-    if current_proxy_metadata.tag == proxy_metadata.tag {
-        let current_proxy = db
-            .get_proxy(&current_proxy_metadata.tag)
-            .await
-            .map_err(|_| ApiErr::DatabaseError(DbErr::UnableToReadRoads))?
-            .ok_or(ApiErr::DatabaseError(DbErr::UnableToReadRoads))?;
-        runtime
-            .set_proxy(&current_proxy.component)
-            .map_err(|_| ApiErr::FailedToSendMessage)?;
+    else {
+        return Ok(StatusCode::OK);
+    };
+    let is_fallback = runtime.fallback_tag() == Some(tag.as_str());
+    db.delete_proxy(tag)
+        .await
+        .map_err(|_| ApiErr::DatabaseError(DbErr::UnableToDeleteRoad))?;
+    if is_active {
+        activate(&db, &runtime).await?;
+    } else if is_fallback {
+        load_fallback_proxy(&runtime, None).await?;
     }
+    runtime.forget_proxy(&proxy);
     Ok(StatusCode::OK)
 }
 
-pub(super) async fn delete_proxy(
+pub(super) async fn get_proxy_configuration(
+    State((db, _)): State<(Arc<RwLock<Database>>, Runtime)>,
+    Path(tag): Path<String>,
+) -> Result<Json<Option<serde_json::Value>>, (StatusCode, Json<serde_json::Value>)> {
+    let db = db.read().await;
+    let document = db
+        .get_proxy_configuration(&tag)
+        .await
+        .map_err(|_| ApiErr::DatabaseError(DbErr::UnableToReadRoads))?;
+    Ok(Json(document))
+}
+
+pub(super) async fn set_proxy_configuration(
     State((db, runtime)): State<(Arc<RwLock<Database>>, Runtime)>,
     Path(tag): Path<String>,
+    headers: HeaderMap,
+    body: String,
 ) -> Result<StatusCode, (StatusCode, Json<serde_json::Value>)> {
+    let document = parse_document(&headers, &body)?;
     let db = db.write().await;
-    if let Some(proxy_metadata) = db
-        .delete_proxy(tag)
+    let exists = db
+        .proxy_exists(&tag)
         .await
-        .map_err(|_| ApiErr::DatabaseError(DbErr::UnableToDeleteRoad))?
-    {
-        let current_proxy_metadata = db
-            .get_current_proxy()
+        .map_err(|_| ApiErr::DatabaseError(DbErr::UnableToReadRoads))?;
+    if exists.is_none() {
+        return Ok(StatusCode::NOT_FOUND);
+    }
+    load_changed(&db, &runtime, &tag, |proxy| {
+        proxy.configuration = Some(document.clone())
+    })
+    .await?;
+    match db.set_proxy_configuration(&tag, &document).await {
+        Ok(_) => Ok(StatusCode::OK),
+        Err(_) => {
+            let error = ApiErr::DatabaseError(DbErr::UnableToUpdateRoad);
+            Err(restore(&db, &runtime, error).await.into())
+        }
+    }
+}
+
+pub(super) async fn get_chain(
+    State((db, _)): State<(Arc<RwLock<Database>>, Runtime)>,
+) -> Result<Json<Vec<ProxyMetadata>>, (StatusCode, Json<serde_json::Value>)> {
+    let db = db.read().await;
+    let chain = db
+        .get_chain()
+        .await
+        .map_err(|_| ApiErr::DatabaseError(DbErr::UnableToReadRoads))?;
+    Ok(Json(chain))
+}
+
+pub(super) async fn set_chain(
+    State((db, runtime)): State<(Arc<RwLock<Database>>, Runtime)>,
+    Json(tags): Json<Vec<String>>,
+) -> Result<StatusCode, (StatusCode, Json<serde_json::Value>)> {
+    let db = db.write().await;
+    let mut proxies = Vec::with_capacity(tags.len());
+    for tag in &tags {
+        let Some(proxy) = db
+            .get_proxy(tag)
             .await
             .map_err(|_| ApiErr::DatabaseError(DbErr::UnableToReadRoads))?
-            .ok_or(ApiErr::DatabaseError(DbErr::UnableToReadRoads))?;
-        if current_proxy_metadata.tag == proxy_metadata.tag {
-            let current_proxy = db
-                .get_proxy(&current_proxy_metadata.tag)
+        else {
+            return Ok(StatusCode::NOT_FOUND);
+        };
+        proxies.push(proxy);
+    }
+    if tags.is_empty() {
+        // Without a chain the current proxy serves requests again.
+        let current = db
+            .get_current_proxy()
+            .await
+            .map_err(|_| ApiErr::DatabaseError(DbErr::UnableToReadRoads))?;
+        if let Some(current) = current {
+            let proxy = db
+                .get_proxy(&current.tag)
                 .await
-                .map_err(|_| ApiErr::DatabaseError(DbErr::UnableToReadRoads))?
-                .ok_or(ApiErr::DatabaseError(DbErr::UnableToReadRoads))?;
-            runtime
-                .set_proxy(&current_proxy.component)
-                .map_err(|_| ApiErr::FailedToSendMessage)?;
+                .map_err(|_| ApiErr::DatabaseError(DbErr::UnableToReadRoads))?;
+            proxies.extend(proxy);
         }
     }
-    Ok(StatusCode::OK)
+    load_chain(&runtime, proxies).await?;
+    match db.set_chain(&tags).await {
+        Ok(_) => Ok(StatusCode::OK),
+        Err(_) => {
+            let error = ApiErr::DatabaseError(DbErr::UnableToUpdateRoad);
+            Err(restore(&db, &runtime, error).await.into())
+        }
+    }
+}
+
+pub(super) async fn rollbacks(
+    State((db, _)): State<(Arc<RwLock<Database>>, Runtime)>,
+) -> Result<Json<Vec<Rollback>>, (StatusCode, Json<serde_json::Value>)> {
+    let db = db.read().await;
+    let rollbacks = db
+        .rollbacks()
+        .await
+        .map_err(|_| ApiErr::DatabaseError(DbErr::UnableToReadRoads))?;
+    Ok(Json(rollbacks))
+}
+
+/// Compiles the component on a blocking thread, large components take long
+/// enough to stall the requests sharing the async worker otherwise.
+async fn validate(runtime: &Runtime, component: Vec<u8>) -> Result<Vec<u8>, ApiErr> {
+    let runtime = runtime.clone();
+    tokio::task::spawn_blocking(move || runtime.validate(&component).map(|()| component))
+        .await
+        .map_err(|e| ApiErr::FailedToLoad(e.into()))?
+        .map_err(ApiErr::IncompatibleComponent)
+}
+
+/// Loads the chain on a blocking thread, like `validate` compiling would
+/// stall the async worker otherwise.
+async fn load_chain(runtime: &Runtime, proxies: Vec<Proxy>) -> Result<(), ApiErr> {
+    let runtime = runtime.clone();
+    tokio::task::spawn_blocking(move || runtime.set_chain(&proxies))
+        .await
+        .map_err(|e| ApiErr::FailedToActivate(e.into()))?
+        .map_err(ApiErr::FailedToActivate)
+}
+
+/// Loads the fallback proxy on a blocking thread, like `load_chain`.
+async fn load_fallback_proxy(runtime: &Runtime, proxy: Option<Proxy>) -> Result<(), ApiErr> {
+    let runtime = runtime.clone();
+    tokio::task::spawn_blocking(move || runtime.set_fallback(proxy.as_ref()))
+        .await
+        .map_err(|e| ApiErr::FailedToActivate(e.into()))?
+        .map_err(ApiErr::FailedToActivate)
+}
+
+/// Hands the chain, or the current proxy without one, and the fallback proxy
+/// to the runtime.
+pub(super) async fn activate(db: &Database, runtime: &Runtime) -> Result<(), ApiErr> {
+    let proxies = db
+        .get_active_proxies()
+        .await
+        .map_err(|_| ApiErr::DatabaseError(DbErr::UnableToReadRoads))?;
+    load_chain(runtime, proxies).await?;
+    load_fallback(db, runtime).await
+}
+
+async fn load_fallback(db: &Database, runtime: &Runtime) -> Result<(), ApiErr> {
+    let Some(tag) = runtime.fallback_tag() else {
+        return Ok(());
+    };
+    let proxy = db
+        .get_proxy(tag)
+        .await
+        .map_err(|_| ApiErr::DatabaseError(DbErr::UnableToReadRoads))?;
+    load_fallback_proxy(runtime, proxy).await
+}
+
+/// Hands the active proxies and the fallback to the runtime with `change`
+/// applied to the one tagged `tag`. Runs before the change is stored, so a
+/// proxy the runtime rejects never reaches the database.
+async fn load_changed(
+    db: &Database,
+    runtime: &Runtime,
+    tag: &str,
+    change: impl Fn(&mut Proxy),
+) -> Result<(), ApiErr> {
+    let mut proxies = db
+        .get_active_proxies()
+        .await
+        .map_err(|_| ApiErr::DatabaseError(DbErr::UnableToReadRoads))?;
+    if proxies.iter().any(|proxy| proxy.metadata.tag == tag) {
+        proxies
+            .iter_mut()
+            .filter(|proxy| proxy.metadata.tag == tag)
+            .for_each(&change);
+        load_chain(runtime, proxies).await?;
+    }
+    if runtime.fallback_tag() == Some(tag) {
+        let fallback = db
+            .get_proxy(tag)
+            .await
+            .map_err(|_| ApiErr::DatabaseError(DbErr::UnableToReadRoads))?;
+        let Some(mut fallback) = fallback else {
+            return Ok(());
+        };
+        change(&mut fallback);
+        if let Err(error) = load_fallback_proxy(runtime, Some(fallback)).await {
+            return Err(restore(db, runtime, error).await);
+        }
+    }
+    Ok(())
+}
+
+/// Brings the runtime back to what the database holds after a change was
+/// loaded but could not be stored, `error` is the one to report.
+async fn restore(db: &Database, runtime: &Runtime, error: ApiErr) -> ApiErr {
+    if let Err(restore_error) = activate(db, runtime).await {
+        tracing::error!(?restore_error, "Failed to restore proxies of the database");
+    }
+    error
+}
+
+/// Configuration documents are JSON unless sent as YAML, they have to be a
+/// mapping so components can look up values by key.
+fn parse_document(headers: &HeaderMap, body: &str) -> Result<serde_json::Value, ApiErr> {
+    let is_yaml = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|content_type| content_type.contains("yaml"));
+    let document: serde_json::Value = if is_yaml {
+        serde_yaml::from_str(body).map_err(|e| ApiErr::InvalidConfiguration(e.to_string()))?
+    } else {
+        serde_json::from_str(body).map_err(|e| ApiErr::InvalidConfiguration(e.to_string()))?
+    };
+    if !document.is_object() {
+        return Err(ApiErr::InvalidConfiguration(
+            "Configuration has to be a mapping".to_string(),
+        ));
+    }
+    Ok(document)
+}
+
+pub(super) async fn stats(
+    State((_, runtime)): State<(Arc<RwLock<Database>>, Runtime)>,
+) -> Json<Stats> {
+    Json(runtime.stats())
 }
@@ -1,11 +1,15 @@
-use axum::{http::StatusCode, Json};
+use axum::{Json, http::StatusCode};
+use runtime::validation::Incompatible;
 
 #[derive(Debug)]
 pub(super) enum Error {
     TagAlreadyExists,
+    ChainIsSet,
     DatabaseError(crate::database::error::Error),
-    FailedToSendMessage,
+    FailedToActivate(anyhow::Error),
     FailedToLoad(anyhow::Error),
+    InvalidConfiguration(String),
+    IncompatibleComponent(Incompatible),
 }
 
 impl From<Error> for (StatusCode, Json<serde_json::Value>) {
@@ -15,15 +19,32 @@ impl From<Error> for (StatusCode, Json<serde_json::Value>) {
                 StatusCode::CONFLICT,
                 format!("Host already exists, use update instead"),
             ),
+            Error::ChainIsSet => (
+                StatusCode::CONFLICT,
+                "A chain is set, clear it before selecting the current proxy".to_string(),
+            ),
             Error::DatabaseError(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
-            Error::FailedToSendMessage => (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                "Failed to send message".to_string(),
+            Error::FailedToActivate(e) => (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                format!("Failed to activate proxy: {:#}", e),
             ),
             Error::FailedToLoad(e) => (
                 StatusCode::INTERNAL_SERVER_ERROR,
                 format!("Failed to load: {}", e),
             ),
+            Error::InvalidConfiguration(e) => (
+                StatusCode::BAD_REQUEST,
+                format!("Invalid configuration: {}", e),
+            ),
+            Error::IncompatibleComponent(incompatible) => {
+                let body = serde_json::json!({
+                    "error": incompatible.to_string(),
+                    "missing_exports": incompatible.missing_exports,
+                    "unsatisfied_imports": incompatible.unsatisfied_imports,
+                    "errors": incompatible.errors,
+                });
+                return (StatusCode::UNPROCESSABLE_ENTITY, Json(body));
+            }
         };
 
         (status, Json(serde_json::json!({ "error": message })))
@@ -8,14 +8,18 @@ use axum::Router;
 use axum::routing::{delete, get, post, put};
 use std::sync::Arc;
 use tokio::sync::RwLock;
+use tokio::sync::broadcast::error::RecvError;
 
 use crate::database::Database;
 use configuration::Configuration;
 use runtime::Runtime;
+use runtime::kv::KeyValueStore;
 
 pub struct API {
     port: u16,
     database: Database,
+    /// Routes of other components, served next to the API's own.
+    routes: Router,
 }
 
 impl API {
@@ -23,11 +27,28 @@ impl API {
         let api = Self {
             port: configuration.port,
             database: Database::new(&configuration.database).await?,
+            routes: Router::new(),
         };
         Ok(api)
     }
 
+    pub fn merge(mut self, routes: Router) -> Self {
+        self.routes = self.routes.merge(routes);
+        self
+    }
+
+    pub fn key_value_store(&self) -> Arc<dyn KeyValueStore> {
+        Arc::new(self.database.clone())
+    }
+
     pub async fn run(self, runtime: Runtime) -> Result<()> {
+        // Serves what was active before the restart, the runtime keeps its
+        // default proxy when nothing is stored.
+        if let Err(error) = endpoints::activate(&self.database, &runtime).await {
+            tracing::error!(?error, "Failed to activate stored proxies");
+        }
+        let database = Arc::new(RwLock::new(self.database));
+        tokio::spawn(watch_rollbacks(database.clone(), runtime.clone()));
         let app = Router::new()
             .route("/proxies/current", get(endpoints::current_proxy))
             .route("/proxies/current/{tag}", get(endpoints::set_current_proxy))
@@ -36,9 +57,50 @@ impl API {
             .route("/proxies/{tag}", get(endpoints::get_proxy))
             .route("/proxies/{tag}", put(endpoints::update_proxy))
             .route("/proxies/{tag}", delete(endpoints::delete_proxy))
-            .with_state((Arc::new(RwLock::new(self.database)), runtime));
+            .route(
+                "/proxies/{tag}/configuration",
+                get(endpoints::get_proxy_configuration),
+            )
+            .route(
+                "/proxies/{tag}/configuration",
+                put(endpoints::set_proxy_configuration),
+            )
+            .route("/chain", get(endpoints::get_chain))
+            .route("/chain", put(endpoints::set_chain))
+            .route("/rollbacks", get(endpoints::rollbacks))
+            .route("/stats", get(endpoints::stats))
+            .with_state((database, runtime))
+            .merge(self.routes);
         let address = format!("0.0.0.0:{}", self.port);
         let listener = tokio::net::TcpListener::bind(address).await?;
         axum::serve(listener, app).await.map_err(|err| err.into())
     }
 }
+
+/// Rolls back proxies the runtime reports as trapping too often.
+async fn watch_rollbacks(db: Arc<RwLock<Database>>, runtime: Runtime) {
+    let mut rollbacks = runtime.subscribe_rollbacks();
+    loop {
+        let rollback = match rollbacks.recv().await {
+            Ok(rollback) => rollback,
+            Err(RecvError::Lagged(_)) => continue,
+            Err(RecvError::Closed) => return,
+        };
+        let db = db.write().await;
+        match db.rollback_proxy(&rollback.tag, &rollback.reason).await {
+            Ok(Some(record)) => {
+                tracing::warn!(from = record.from_tag, to = ?record.to_tag, "Rolled back proxy");
+                if let Err(error) = endpoints::activate(&db, &runtime).await {
+                    tracing::error!(?error, "Failed to activate proxy after rollback");
+                }
+            }
+            Ok(None) => {
+                tracing::warn!(
+                    tag = rollback.tag,
+                    "Proxy to roll back is not serving requests"
+                );
+            }
+            Err(error) => tracing::error!(%error, "Failed to roll back proxy"),
+        }
+    }
+}
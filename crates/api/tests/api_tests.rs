@@ -0,0 +1,228 @@
+use anyhow::{Result, anyhow, bail};
+use rama::http::{Body, Request};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use uuid::Uuid;
+
+use api::API;
+use api::configuration::{Configuration, database};
+use api::database::Database;
+use runtime::Runtime;
+use runtime::configuration::fallback;
+use runtime::resolution::Resolution;
+
+#[tokio::test]
+async fn stored_proxy_is_served_after_restart() -> Result<()> {
+    let files = DatabaseFiles::new();
+    let database = Database::new(&files.configuration()).await?;
+    database
+        .create_proxy("respond".to_string(), component("respond")?)
+        .await?;
+    database.set_current_proxy("respond").await?;
+    drop(database);
+
+    let (runtime, _) = start(&files).await?;
+
+    for _ in 0..100 {
+        if let Resolution::Respond(response) = runtime.process(request()?).await? {
+            assert_eq!(response.status(), 403);
+            return Ok(());
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    bail!("Stored proxy was not activated after restart");
+}
+
+#[tokio::test]
+async fn default_proxy_is_served_without_stored_ones() -> Result<()> {
+    let files = DatabaseFiles::new();
+
+    let (runtime, port) = start(&files).await?;
+
+    send(port, "GET", "/stats", "").await?;
+    let resolution = runtime.process(request()?).await?;
+    assert!(matches!(resolution, Resolution::Forward(_)));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn chain_the_runtime_rejects_is_not_stored() -> Result<()> {
+    let files = DatabaseFiles::new();
+    let database = Database::new(&files.configuration()).await?;
+    database
+        .create_proxy("broken".to_string(), b"no component".to_vec())
+        .await?;
+    let (runtime, port) = start(&files).await?;
+
+    let (status, body) = send(port, "PUT", "/chain", r#"["broken"]"#).await?;
+    assert_eq!(status, 422);
+    assert!(body.contains("Failed to activate proxy"), "{}", body);
+    assert!(database.get_chain().await?.is_empty());
+    let resolution = runtime.process(request()?).await?;
+    assert!(matches!(resolution, Resolution::Forward(_)));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn current_proxy_the_runtime_rejects_is_not_stored() -> Result<()> {
+    let files = DatabaseFiles::new();
+    let database = Database::new(&files.configuration()).await?;
+    database
+        .create_proxy("broken".to_string(), b"no component".to_vec())
+        .await?;
+    let (_, port) = start(&files).await?;
+
+    let (status, body) = send(port, "GET", "/proxies/current/broken", "").await?;
+    assert_eq!(status, 422);
+    assert!(body.contains("Failed to activate proxy"), "{}", body);
+    assert!(database.get_current_proxy().await?.is_none());
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn deleted_fallback_proxy_is_no_longer_served() -> Result<()> {
+    let files = DatabaseFiles::new();
+    let database = Database::new(&files.configuration()).await?;
+    database
+        .create_proxy("trap".to_string(), component("start_trap")?)
+        .await?;
+    database
+        .create_proxy("fallback".to_string(), component("respond")?)
+        .await?;
+    database.set_current_proxy("trap").await?;
+    let configuration = runtime::configuration::Configuration {
+        fallback: Some(fallback::Configuration::Proxy(fallback::Proxy {
+            tag: "fallback".to_string(),
+        })),
+        ..Default::default()
+    };
+    let (runtime, port) = start_with(&files, &configuration).await?;
+
+    send(port, "GET", "/stats", "").await?;
+    assert!(matches!(
+        runtime.process(request()?).await?,
+        Resolution::Respond(_)
+    ));
+    let (status, _) = send(port, "DELETE", "/proxies/fallback", "").await?;
+    assert_eq!(status, 200);
+    assert!(runtime.process(request()?).await.is_err());
+
+    Ok(())
+}
+
+/// Runs the API on a free port with the database in `files`, the runtime
+/// serves `forward.wat` by default. Returns the runtime and the port.
+async fn start(files: &DatabaseFiles) -> Result<(Runtime, u16)> {
+    start_with(files, &Default::default()).await
+}
+
+/// Like `start`, with the runtime set up by `runtime_configuration`.
+async fn start_with(
+    files: &DatabaseFiles,
+    runtime_configuration: &runtime::configuration::Configuration,
+) -> Result<(Runtime, u16)> {
+    let port = std::net::TcpListener::bind("127.0.0.1:0")?
+        .local_addr()?
+        .port();
+    let configuration = Configuration {
+        port,
+        database: files.configuration(),
+        proxys: Vec::new(),
+    };
+    let api = API::new(&configuration).await?;
+    let runtime = Runtime::new(
+        runtime_configuration,
+        api.key_value_store(),
+        &component("forward")?,
+    )?;
+    tokio::spawn(api.run(runtime.clone()));
+    Ok((runtime, port))
+}
+
+/// Sends a request to the API once it accepts connections, returns the status
+/// and the body of the response.
+async fn send(port: u16, method: &str, path: &str, body: &str) -> Result<(u16, String)> {
+    let mut stream = None;
+    for _ in 0..100 {
+        match TcpStream::connect(("127.0.0.1", port)).await {
+            Ok(connected) => {
+                stream = Some(connected);
+                break;
+            }
+            Err(_) => tokio::time::sleep(Duration::from_millis(10)).await,
+        }
+    }
+    let Some(mut stream) = stream else {
+        bail!("API does not accept connections on port {}", port);
+    };
+    let request = format!(
+        "{} {} HTTP/1.1\r\nHost: localhost\r\nContent-Type: application/json\r\n\
+        Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+        method,
+        path,
+        body.len(),
+        body
+    );
+    stream.write_all(request.as_bytes()).await?;
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await?;
+    let status = response
+        .split(' ')
+        .nth(1)
+        .and_then(|status| status.parse().ok())
+        .ok_or_else(|| anyhow!("Invalid response {:?}", response))?;
+    let body = response
+        .split_once("\r\n\r\n")
+        .map(|(_, body)| body.to_string())
+        .unwrap_or_default();
+    Ok((status, body))
+}
+
+fn component(name: &str) -> Result<Vec<u8>> {
+    let path = format!(
+        "{}/../runtime/tests/components/{}.wat",
+        env!("CARGO_MANIFEST_DIR"),
+        name
+    );
+    Ok(wat::parse_file(path)?)
+}
+
+fn request() -> Result<Request> {
+    let request = Request::builder()
+        .uri("http://example.com/")
+        .body(Body::empty())?;
+    Ok(request)
+}
+
+/// Database named after a fresh uuid, its file is removed on drop.
+struct DatabaseFiles {
+    uuid: Uuid,
+}
+
+impl DatabaseFiles {
+    fn new() -> Self {
+        Self {
+            uuid: Uuid::new_v4(),
+        }
+    }
+
+    fn configuration(&self) -> database::Configuration {
+        database::Configuration {
+            name: self.uuid.to_string(),
+            path: ".".to_string(),
+        }
+    }
+}
+
+impl Drop for DatabaseFiles {
+    fn drop(&mut self) {
+        let path = format!("./{}.sqlite", self.uuid);
+        if let Err(e) = std::fs::remove_file(path) {
+            println!("Error removing sqlite db file: {}", e);
+        }
+    }
+}
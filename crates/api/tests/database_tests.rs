@@ -1,7 +1,13 @@
 use anyhow::{Result, bail};
+use rama::http::{Body, Request};
+use std::sync::Arc;
+use tokio::task::JoinSet;
 use uuid::Uuid;
 
 use api::{configuration::database::Configuration, database::Database};
+use runtime::Runtime;
+use runtime::proxy::Proxy;
+use runtime::resolution::Resolution;
 
 #[tokio::test]
 async fn after_setup_all_proxies_empty() -> Result<()> {
@@ -206,6 +212,318 @@ async fn set_current_proxy() -> Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn proxy_configuration() -> Result<()> {
+    let database = &DatabaseWrapper::setup().await?.database;
+
+    const TAG: &str = "alpha:v1.0.0";
+    let document = serde_json::json!({ "mode": "strict", "retries": 3 });
+    let maybe_proxy_metadata = database.set_proxy_configuration(TAG, &document).await?;
+    assert!(maybe_proxy_metadata.is_none());
+
+    database.create_proxy(TAG.to_string(), vec![0; 10]).await?;
+    let maybe_proxy = database.get_proxy(TAG).await?;
+    let Some(proxy) = maybe_proxy else {
+        bail!("Proxy does not exist after creation");
+    };
+    assert!(proxy.configuration.is_none());
+
+    let maybe_proxy_metadata = database.set_proxy_configuration(TAG, &document).await?;
+    assert!(maybe_proxy_metadata.is_some());
+    let stored_document = database.get_proxy_configuration(TAG).await?;
+    assert_eq!(stored_document, Some(document.clone()));
+
+    let maybe_proxy = database.get_proxy(TAG).await?;
+    let Some(proxy) = maybe_proxy else {
+        bail!("Proxy does not exist after setting configuration");
+    };
+    assert_eq!(proxy.configuration, Some(document));
+    let entries = proxy.configuration_entries();
+    assert_eq!(entries.get("mode").map(String::as_str), Some("strict"));
+    assert_eq!(entries.get("retries").map(String::as_str), Some("3"));
+
+    database.delete_proxy(TAG.to_string()).await?;
+    let stored_document = database.get_proxy_configuration(TAG).await?;
+    assert!(stored_document.is_none());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn chain() -> Result<()> {
+    let database = &DatabaseWrapper::setup().await?.database;
+
+    const TAGS: [&str; 3] = ["auth:v1.0.0", "rate-limit:v1.0.0", "rewrite:v1.0.0"];
+    let tags: Vec<String> = TAGS.iter().map(|tag| tag.to_string()).collect();
+    let maybe_chain = database.set_chain(&tags).await?;
+    assert!(maybe_chain.is_none());
+
+    for tag in TAGS {
+        database.create_proxy(tag.to_string(), vec![0; 10]).await?;
+    }
+    let maybe_chain = database.set_chain(&tags).await?;
+    let Some(chain) = maybe_chain else {
+        bail!("Chain was not set although all proxies exist");
+    };
+    let chain_tags: Vec<&str> = chain.iter().map(|proxy| proxy.tag.as_str()).collect();
+    assert_eq!(chain_tags, TAGS);
+
+    let active = database.get_active_proxies().await?;
+    assert_eq!(active.len(), 3);
+    assert!(database.is_active_proxy(TAGS[1]).await?);
+
+    database.delete_proxy(TAGS[1].to_string()).await?;
+    let chain = database.get_chain().await?;
+    let chain_tags: Vec<&str> = chain.iter().map(|proxy| proxy.tag.as_str()).collect();
+    assert_eq!(chain_tags, [TAGS[0], TAGS[2]]);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn chain_is_replaced_as_a_whole() -> Result<()> {
+    let database = &DatabaseWrapper::setup().await?.database;
+
+    const TAGS: [&str; 2] = ["auth:v1.0.0", "rewrite:v1.0.0"];
+    for tag in TAGS {
+        database.create_proxy(tag.to_string(), vec![0; 10]).await?;
+    }
+    let tags: Vec<String> = TAGS.iter().map(|tag| tag.to_string()).collect();
+    database.set_chain(&tags).await?;
+
+    let replacement = vec![TAGS[1].to_string(), "missing:v1.0.0".to_string()];
+    assert!(database.set_chain(&replacement).await?.is_none());
+    let chain = database.get_chain().await?;
+    let chain_tags: Vec<&str> = chain.iter().map(|proxy| proxy.tag.as_str()).collect();
+    assert_eq!(chain_tags, TAGS);
+
+    let Some(chain) = database.set_chain(&[TAGS[1].to_string()]).await? else {
+        bail!("Chain was not set although the proxy exists");
+    };
+    let chain_tags: Vec<&str> = chain.iter().map(|proxy| proxy.tag.as_str()).collect();
+    assert_eq!(chain_tags, [TAGS[1]]);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn empty_chain_falls_back_to_current_proxy() -> Result<()> {
+    let database = &DatabaseWrapper::setup().await?.database;
+
+    const TAG: &str = "alpha:v1.0.0";
+    assert!(database.get_active_proxies().await?.is_empty());
+
+    database.create_proxy(TAG.to_string(), vec![0; 10]).await?;
+    database.set_current_proxy(TAG).await?;
+    database.set_chain(&[]).await?;
+    let active = database.get_active_proxies().await?;
+    assert_eq!(active.len(), 1);
+    assert_eq!(active[0].metadata.tag, TAG);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn rollback_proxy() -> Result<()> {
+    let database = &DatabaseWrapper::setup().await?.database;
+
+    const STABLE: &str = "alpha:v1.0.0";
+    const BROKEN: &str = "alpha:v1.1.0";
+    database
+        .create_proxy(STABLE.to_string(), vec![0; 10])
+        .await?;
+    database
+        .create_proxy(BROKEN.to_string(), vec![1; 10])
+        .await?;
+    database.set_current_proxy(STABLE).await?;
+    database.set_current_proxy(BROKEN).await?;
+
+    let maybe_rollback = database.rollback_proxy(STABLE, "traps").await?;
+    assert!(maybe_rollback.is_none());
+
+    let Some(rollback) = database.rollback_proxy(BROKEN, "traps").await? else {
+        bail!("Current proxy was not rolled back");
+    };
+    assert_eq!(rollback.from_tag, BROKEN);
+    assert_eq!(rollback.to_tag.as_deref(), Some(STABLE));
+    assert_eq!(rollback.reason, "traps");
+
+    let Some(current_proxy_metadata) = database.get_current_proxy().await? else {
+        bail!("No current proxy after rollback");
+    };
+    assert_eq!(current_proxy_metadata.tag, STABLE);
+
+    let Some(rollback) = database.rollback_proxy(STABLE, "traps").await? else {
+        bail!("Current proxy was not rolled back");
+    };
+    assert!(rollback.to_tag.is_none());
+    assert_eq!(database.rollbacks().await?.len(), 2);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn rollback_removes_chain_member() -> Result<()> {
+    let database = &DatabaseWrapper::setup().await?.database;
+
+    const TAGS: [&str; 2] = ["auth:v1.0.0", "rewrite:v1.0.0"];
+    const CURRENT: &str = "alpha:v1.0.0";
+    for tag in TAGS.iter().chain([&CURRENT]) {
+        database.create_proxy(tag.to_string(), vec![0; 10]).await?;
+    }
+    database.set_current_proxy(CURRENT).await?;
+    let tags: Vec<String> = TAGS.iter().map(|tag| tag.to_string()).collect();
+    database.set_chain(&tags).await?;
+
+    assert!(database.rollback_proxy(CURRENT, "traps").await?.is_none());
+
+    let Some(rollback) = database.rollback_proxy(TAGS[0], "traps").await? else {
+        bail!("Chain member was not rolled back");
+    };
+    assert_eq!(rollback.from_tag, TAGS[0]);
+    assert!(rollback.to_tag.is_none());
+    let chain = database.get_chain().await?;
+    let chain_tags: Vec<&str> = chain.iter().map(|proxy| proxy.tag.as_str()).collect();
+    assert_eq!(chain_tags, [TAGS[1]]);
+    assert!(database.proxy_exists(TAGS[0]).await?.is_some());
+    let Some(current_proxy_metadata) = database.get_current_proxy().await? else {
+        bail!("Current proxy changed by rolling back a chain member");
+    };
+    assert_eq!(current_proxy_metadata.tag, CURRENT);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn kv_set_and_get() -> Result<()> {
+    let database = &DatabaseWrapper::setup().await?.database;
+
+    const NAMESPACE: &str = "alpha:v1.0.0";
+    let value = database.kv_get(NAMESPACE, "flag").await?;
+    assert!(value.is_none());
+
+    database.kv_set(NAMESPACE, "flag", vec![1, 2, 3]).await?;
+    let value = database.kv_get(NAMESPACE, "flag").await?;
+    assert_eq!(value, Some(vec![1, 2, 3]));
+
+    database.kv_set(NAMESPACE, "flag", vec![4]).await?;
+    let value = database.kv_get(NAMESPACE, "flag").await?;
+    assert_eq!(value, Some(vec![4]));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn kv_namespaces_are_isolated() -> Result<()> {
+    let database = &DatabaseWrapper::setup().await?.database;
+
+    database.kv_set("alpha:v1.0.0", "flag", vec![1]).await?;
+    let value = database.kv_get("beta:v1.0.0", "flag").await?;
+    assert!(value.is_none());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn kv_delete() -> Result<()> {
+    let database = &DatabaseWrapper::setup().await?.database;
+
+    const NAMESPACE: &str = "alpha:v1.0.0";
+    database.kv_set(NAMESPACE, "flag", vec![1]).await?;
+    database.kv_delete(NAMESPACE, "flag").await?;
+    let value = database.kv_get(NAMESPACE, "flag").await?;
+    assert!(value.is_none());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn kv_increment() -> Result<()> {
+    let database = &DatabaseWrapper::setup().await?.database;
+
+    const NAMESPACE: &str = "alpha:v1.0.0";
+    assert_eq!(database.kv_increment(NAMESPACE, "hits", 1).await?, 1);
+    assert_eq!(database.kv_increment(NAMESPACE, "hits", 5).await?, 6);
+    assert_eq!(database.kv_increment(NAMESPACE, "hits", -2).await?, 4);
+
+    let value = database.kv_get(NAMESPACE, "hits").await?;
+    assert_eq!(value, Some(b"4".to_vec()));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn kv_increment_keeps_value_that_is_no_integer() -> Result<()> {
+    let database = &DatabaseWrapper::setup().await?.database;
+
+    const NAMESPACE: &str = "alpha:v1.0.0";
+    database.kv_set(NAMESPACE, "hits", b"many".to_vec()).await?;
+    assert!(database.kv_increment(NAMESPACE, "hits", 1).await.is_err());
+
+    let value = database.kv_get(NAMESPACE, "hits").await?;
+    assert_eq!(value, Some(b"many".to_vec()));
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn kv_increments_of_concurrent_guests_all_count() -> Result<()> {
+    let database = &DatabaseWrapper::setup().await?.database;
+
+    const NAMESPACE: &str = "counter:v1.0.0";
+    let runtime = counter(database, NAMESPACE)?;
+    let mut requests = JoinSet::new();
+    for _ in 0..20 {
+        let runtime = runtime.clone();
+        requests.spawn(async move {
+            let resolution = runtime.process(request()?).await?;
+            anyhow::Ok(resolution)
+        });
+    }
+    while let Some(resolution) = requests.join_next().await {
+        assert!(matches!(resolution??, Resolution::Forward(_)));
+    }
+
+    let value = database.kv_get(NAMESPACE, "hits").await?;
+    assert_eq!(value, Some(b"20".to_vec()));
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn kv_error_is_returned_to_guest() -> Result<()> {
+    let database = &DatabaseWrapper::setup().await?.database;
+
+    const NAMESPACE: &str = "counter:v1.0.0";
+    database.kv_set(NAMESPACE, "hits", b"many".to_vec()).await?;
+    let runtime = counter(database, NAMESPACE)?;
+
+    let Resolution::Respond(response) = runtime.process(request()?).await? else {
+        bail!("Component did not see the failed increment");
+    };
+    assert_eq!(response.status(), 500);
+
+    Ok(())
+}
+
+/// Runtime serving a component that increments the key `hits` as `tag`.
+fn counter(database: &Database, tag: &str) -> Result<Runtime> {
+    let component = wat::parse_file(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/../runtime/tests/components/counter.wat"
+    ))?;
+    let runtime = Runtime::new(&Default::default(), Arc::new(database.clone()), &component)?;
+    runtime.set_proxy(&Proxy::new(tag.to_string(), component))?;
+    Ok(runtime)
+}
+
+fn request() -> Result<Request> {
+    let request = Request::builder()
+        .uri("http://example.com/")
+        .body(Body::empty())?;
+    Ok(request)
+}
+
 struct DatabaseWrapper {
     uuid: Uuid,
     database: Database,
@@ -0,0 +1,75 @@
+//! Latency of forwarding to a local keep-alive upstream, with a fresh client
+//! per request as `serve` used to do and with the shared pooled client.
+
+use criterion::{Criterion, criterion_group, criterion_main};
+use rama::http::client::EasyHttpWebClient;
+use rama::http::dep::http_body_util::BodyExt;
+use rama::http::{Body, Request};
+use rama::{Context, Service};
+use std::net::SocketAddr;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::runtime::Runtime;
+
+use gateway::client::UpstreamClient;
+use gateway::configuration::client::Configuration;
+
+fn forward(c: &mut Criterion) {
+    let runtime = Runtime::new().unwrap();
+    let address = runtime.block_on(stub());
+    let uri = format!("http://{}/", address);
+    let request = || Request::builder().uri(&uri).body(Body::empty()).unwrap();
+
+    let mut group = c.benchmark_group("forward");
+    group.bench_function("client per request", |b| {
+        b.to_async(&runtime).iter(|| async {
+            let response = EasyHttpWebClient::default()
+                .serve(Context::default(), request())
+                .await
+                .unwrap();
+            response.into_body().collect().await.unwrap();
+        })
+    });
+    let client = UpstreamClient::new(&Configuration::default()).unwrap();
+    group.bench_function("shared client", |b| {
+        b.to_async(&runtime).iter(|| async {
+            let response = client.send(request(), &Default::default()).await.unwrap();
+            response.into_body().collect().await.unwrap();
+        })
+    });
+    group.finish();
+}
+
+/// HTTP/1.1 server keeping connections alive and answering every request
+/// with a short body.
+async fn stub() -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            tokio::spawn(keep_alive(stream));
+        }
+    });
+    address
+}
+
+async fn keep_alive(mut stream: TcpStream) {
+    const RESPONSE: &[u8] = b"HTTP/1.1 200 OK\r\ncontent-length: 5\r\n\r\nhello";
+    let mut buffer = Vec::new();
+    let mut chunk = [0; 4096];
+    loop {
+        while let Some(end) = buffer.windows(4).position(|window| window == b"\r\n\r\n") {
+            buffer.drain(..end + 4);
+            if stream.write_all(RESPONSE).await.is_err() {
+                return;
+            }
+        }
+        match stream.read(&mut chunk).await {
+            Ok(0) | Err(_) => return,
+            Ok(read) => buffer.extend_from_slice(&chunk[..read]),
+        }
+    }
+}
+
+criterion_group!(benches, forward);
+criterion_main!(benches);
@@ -0,0 +1,213 @@
+use rama::http::Request;
+use rama::http::header::COOKIE;
+use sha2::{Digest, Sha256};
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::configuration::balancing::{Configuration, HashKey};
+use crate::health::Health;
+use crate::upstream::Endpoint;
+
+/// Points every unit of weight places on the hash ring, more points spread
+/// keys more evenly.
+const POINTS_PER_WEIGHT: usize = 64;
+
+pub(crate) struct Member {
+    pub(crate) endpoint: Endpoint,
+    pub(crate) health: Arc<Health>,
+    weight: u32,
+    outstanding: Arc<AtomicUsize>,
+}
+
+impl Member {
+    /// Requests currently in flight to the endpoint.
+    pub(crate) fn outstanding(&self) -> usize {
+        self.outstanding.load(Ordering::Relaxed)
+    }
+}
+
+/// Counts a request as in flight to its endpoint until dropped.
+pub(crate) struct Outstanding(Arc<AtomicUsize>);
+
+impl Drop for Outstanding {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+enum Strategy {
+    RoundRobin,
+    LeastOutstanding,
+    ConsistentHash { key: HashKey, ring: Ring },
+}
+
+/// Chooses the endpoint of an upstream for each request, unhealthy endpoints
+/// are skipped.
+pub(crate) struct Balancer {
+    members: Vec<Member>,
+    strategy: Strategy,
+    next: AtomicUsize,
+}
+
+impl Balancer {
+    pub(crate) fn new(endpoints: Vec<(Endpoint, u32)>, configuration: &Configuration) -> Self {
+        let members = endpoints
+            .into_iter()
+            .map(|(endpoint, weight)| Member {
+                endpoint,
+                health: Default::default(),
+                weight,
+                outstanding: Default::default(),
+            })
+            .collect::<Vec<_>>();
+        let strategy = match configuration {
+            Configuration::RoundRobin => Strategy::RoundRobin,
+            Configuration::LeastOutstanding => Strategy::LeastOutstanding,
+            Configuration::ConsistentHash(key) => Strategy::ConsistentHash {
+                key: key.clone(),
+                ring: Ring::new(
+                    &members
+                        .iter()
+                        .map(|member| (member.endpoint.key(), member.weight))
+                        .collect::<Vec<_>>(),
+                ),
+            },
+        };
+        Self {
+            members,
+            strategy,
+            next: AtomicUsize::new(0),
+        }
+    }
+
+    pub(crate) fn members(&self) -> &[Member] {
+        &self.members
+    }
+
+    /// Picks the endpoint for the request, `client` is the address of the
+    /// peer that sent it. `None` if no endpoint is healthy.
+    pub(crate) fn pick(
+        &self,
+        request: &Request,
+        client: Option<IpAddr>,
+    ) -> Option<(Endpoint, Outstanding)> {
+        let index = match &self.strategy {
+            Strategy::RoundRobin => self.round_robin(),
+            Strategy::LeastOutstanding => self.least_outstanding(),
+            Strategy::ConsistentHash { key, ring } => match hash_key(key, request, client) {
+                Some(hash) => ring.owner(hash, |index| self.is_healthy(index)),
+                None => self.round_robin(),
+            },
+        }?;
+        let member = &self.members[index];
+        member.outstanding.fetch_add(1, Ordering::Relaxed);
+        Some((
+            member.endpoint.clone(),
+            Outstanding(member.outstanding.clone()),
+        ))
+    }
+
+    fn is_healthy(&self, index: usize) -> bool {
+        self.members[index].health.is_healthy()
+    }
+
+    fn round_robin(&self) -> Option<usize> {
+        let healthy_weight = (0..self.members.len())
+            .filter(|index| self.is_healthy(*index))
+            .map(|index| self.members[index].weight as usize)
+            .sum::<usize>();
+        if healthy_weight == 0 {
+            return None;
+        }
+        let mut slot = self.next.fetch_add(1, Ordering::Relaxed) % healthy_weight;
+        for (index, member) in self.members.iter().enumerate() {
+            if !self.is_healthy(index) {
+                continue;
+            }
+            if slot < member.weight as usize {
+                return Some(index);
+            }
+            slot -= member.weight as usize;
+        }
+        None
+    }
+
+    /// Ties are broken by starting the scan at the next member in turn, so
+    /// an idle upstream is still spread evenly.
+    fn least_outstanding(&self) -> Option<usize> {
+        let count = self.members.len();
+        let start = self.next.fetch_add(1, Ordering::Relaxed);
+        (0..count)
+            .map(|offset| (start + offset) % count)
+            .filter(|index| self.is_healthy(*index))
+            .min_by(|a, b| {
+                let (a, b) = (&self.members[*a], &self.members[*b]);
+                let a_load = a.outstanding.load(Ordering::Relaxed) as u64 * b.weight as u64;
+                let b_load = b.outstanding.load(Ordering::Relaxed) as u64 * a.weight as u64;
+                a_load.cmp(&b_load)
+            })
+    }
+}
+
+/// Hash ring of consistent hashing, members are named by their index.
+pub struct Ring {
+    /// Points sorted by hash, each naming the index of its member.
+    points: Vec<(u64, usize)>,
+}
+
+impl Ring {
+    /// Places points for every member given by name and weight, the same
+    /// members always make the same ring.
+    pub fn new(members: &[(String, u32)]) -> Self {
+        let mut points = members
+            .iter()
+            .enumerate()
+            .flat_map(|(index, (name, weight))| {
+                (0..*weight as usize * POINTS_PER_WEIGHT)
+                    .map(move |point| (hash(format!("{}#{}", name, point).as_bytes()), index))
+            })
+            .collect::<Vec<_>>();
+        points.sort_unstable();
+        Self { points }
+    }
+
+    /// The first point at or after the hash owns it, wrapping around the
+    /// ring. Points of unhealthy members pass the key on to the next one.
+    pub fn owner(&self, hash: u64, is_healthy: impl Fn(usize) -> bool) -> Option<usize> {
+        let position = self.points.partition_point(|(point, _)| *point < hash);
+        self.points[position..]
+            .iter()
+            .chain(&self.points[..position])
+            .map(|(_, index)| *index)
+            .find(|index| is_healthy(*index))
+    }
+}
+
+fn hash_key(key: &HashKey, request: &Request, client: Option<IpAddr>) -> Option<u64> {
+    match key {
+        HashKey::Header(name) => request
+            .headers()
+            .get(name.as_str())
+            .map(|value| hash(value.as_bytes())),
+        HashKey::Cookie(name) => request
+            .headers()
+            .get_all(COOKIE)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(';'))
+            .filter_map(|cookie| cookie.trim().split_once('='))
+            .find(|(cookie, _)| *cookie == name.as_str())
+            .map(|(_, value)| hash(value.as_bytes())),
+        HashKey::Ip => client.map(|client| hash(client.to_string().as_bytes())),
+    }
+}
+
+/// Stable across builds and restarts, unlike the standard library's hasher,
+/// so keys keep their endpoint when the gateway is upgraded.
+fn hash(value: &[u8]) -> u64 {
+    let digest = Sha256::digest(value);
+    let mut prefix = [0; 8];
+    prefix.copy_from_slice(&digest[..8]);
+    u64::from_be_bytes(prefix)
+}
@@ -0,0 +1,191 @@
+use anyhow::{Result, anyhow};
+use rama::error::{BoxError, OpaqueError};
+use rama::http::client::EasyHttpWebClient;
+use rama::http::dep::http_body_util::BodyExt;
+use rama::http::{Body, Request, Response};
+use rama::net::client::pool::http::HttpPooledConnectorConfig;
+use rama::service::BoxService;
+use rama::tcp::client::service::TcpConnector;
+use rama::tls::boring::client::TlsConnectorDataBuilder;
+use rama::{Context, Service};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::Semaphore;
+
+use crate::configuration::{client::Configuration, timeouts};
+
+/// Hosts tracked before idle ones are forgotten.
+const MAX_TRACKED_HOSTS: usize = 1024;
+
+#[derive(Debug)]
+pub enum Error {
+    /// Waiting for a free slot, connecting or waiting for the response
+    /// headers took too long.
+    Timeout,
+    Failed(anyhow::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Timeout => write!(f, "Upstream did not respond in time"),
+            Error::Failed(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Upstream client shared by all forwarded requests, connections are kept
+/// alive between them.
+#[derive(Clone)]
+pub struct UpstreamClient {
+    client: BoxService<(), Request, Response, OpaqueError>,
+    hosts: Arc<Mutex<HashMap<String, Arc<Semaphore>>>>,
+    max_requests_per_host: usize,
+    pool_timeout: Duration,
+}
+
+impl UpstreamClient {
+    pub fn new(configuration: &Configuration) -> Result<Self> {
+        let pool = HttpPooledConnectorConfig {
+            max_total: configuration.pool_size,
+            max_active: configuration.pool_size,
+            wait_for_pool_timeout: Some(Duration::from_millis(configuration.pool_timeout_ms)),
+            idle_timeout: Some(Duration::from_millis(configuration.idle_timeout_ms)),
+        };
+        let tls = TlsConnectorDataBuilder::new_http_auto().into_shared_builder();
+        let client = EasyHttpWebClient::builder()
+            .with_custom_transport_connector(TimeoutConnector {
+                inner: TcpConnector::new(),
+            })
+            .with_tls_proxy_support_using_boringssl()
+            .with_proxy_support()
+            .with_tls_support_using_boringssl(Some(tls))
+            .with_connection_pool(pool)
+            .map_err(|e| anyhow!("Failed to create connection pool: {}", e))?
+            .build()
+            .boxed();
+        let client = Self {
+            client,
+            hosts: Default::default(),
+            max_requests_per_host: configuration.max_requests_per_host,
+            pool_timeout: Duration::from_millis(configuration.pool_timeout_ms),
+        };
+        Ok(client)
+    }
+
+    /// Sends the request once its host is below the request limit, the slot
+    /// is held until the response body is dropped. Waiting for the slot is
+    /// bounded by the pool timeout and counts towards the read timeout.
+    pub async fn send(
+        &self,
+        request: Request,
+        timeouts: &timeouts::Configuration,
+    ) -> Result<Response, Error> {
+        let host = request
+            .uri()
+            .authority()
+            .map(|authority| authority.to_string())
+            .unwrap_or_default();
+        let connect_timeout = ConnectTimeout {
+            timeout: Duration::from_millis(timeouts.connect_timeout_ms),
+            timed_out: Default::default(),
+        };
+        let mut context = Context::default();
+        context.insert(connect_timeout.clone());
+
+        let attempt = async {
+            let semaphore = self.host(&host).map_err(Error::Failed)?;
+            let permit = tokio::time::timeout(self.pool_timeout, semaphore.acquire_owned())
+                .await
+                .map_err(|_| Error::Timeout)?
+                .map_err(|e| Error::Failed(e.into()))?;
+            let response = self.client.serve(context, request).await.map_err(|e| {
+                match connect_timeout.timed_out.load(Ordering::Relaxed) {
+                    true => Error::Timeout,
+                    false => Error::Failed(anyhow!("{}", e)),
+                }
+            })?;
+            Ok(hold_until_dropped(response, permit))
+        };
+        let read_timeout = Duration::from_millis(timeouts.read_timeout_ms);
+        tokio::time::timeout(read_timeout, attempt)
+            .await
+            .map_err(|_| Error::Timeout)?
+    }
+
+    fn host(&self, host: &str) -> Result<Arc<Semaphore>> {
+        let mut hosts = self
+            .hosts
+            .lock()
+            .map_err(|e| anyhow!("Failed to acquire lock of hosts: {}", e))?;
+        if hosts.len() >= MAX_TRACKED_HOSTS && !hosts.contains_key(host) {
+            let max = self.max_requests_per_host;
+            hosts.retain(|_, semaphore| semaphore.available_permits() < max);
+        }
+        let semaphore = hosts
+            .entry(host.to_string())
+            .or_insert_with(|| Arc::new(Semaphore::new(self.max_requests_per_host)));
+        Ok(semaphore.clone())
+    }
+}
+
+/// Keeps `guard` alive until the body of the response is dropped.
+pub(crate) fn hold_until_dropped<T>(response: Response, guard: T) -> Response
+where
+    T: Send + Sync + 'static,
+{
+    response.map(|body| {
+        Body::new(body.map_frame(move |frame| {
+            let _ = &guard;
+            frame
+        }))
+    })
+}
+
+/// Connect timeout of a request, `timed_out` tells a timeout apart from other
+/// failures once the pooled client wrapped the error.
+#[derive(Debug, Clone)]
+struct ConnectTimeout {
+    timeout: Duration,
+    timed_out: Arc<AtomicBool>,
+}
+
+/// Transport connector bounded by the connect timeout of the request, the
+/// timeout travels in the context as connections are shared across upstreams.
+#[derive(Debug, Clone)]
+struct TimeoutConnector<S> {
+    inner: S,
+}
+
+impl<State, Request, S> Service<State, Request> for TimeoutConnector<S>
+where
+    State: Clone + Send + Sync + 'static,
+    Request: Send + 'static,
+    S: Service<State, Request, Error: Into<BoxError>>,
+{
+    type Response = S::Response;
+    type Error = BoxError;
+
+    async fn serve(
+        &self,
+        context: Context<State>,
+        request: Request,
+    ) -> Result<Self::Response, Self::Error> {
+        let Some(connect_timeout) = context.get::<ConnectTimeout>().cloned() else {
+            return self.inner.serve(context, request).await.map_err(Into::into);
+        };
+        let connect = self.inner.serve(context, request);
+        match tokio::time::timeout(connect_timeout.timeout, connect).await {
+            Ok(result) => result.map_err(Into::into),
+            Err(elapsed) => {
+                connect_timeout.timed_out.store(true, Ordering::Relaxed);
+                Err(elapsed.into())
+            }
+        }
+    }
+}
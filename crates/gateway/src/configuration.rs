@@ -1,3 +1,10 @@
+pub mod balancing;
+pub mod client;
+pub mod health_check;
+pub mod retry;
+pub mod timeouts;
+pub mod tls;
+pub mod upstream;
 mod validation;
 
 #[derive(Debug, serde::Deserialize, garde::Validate)]
@@ -5,10 +12,36 @@ pub struct Configuration {
     #[garde(custom(validation::is_valid_port))]
     #[serde(default)]
     pub port: u16,
+    #[garde(dive)]
+    #[serde(default)]
+    pub upstreams: Vec<upstream::Configuration>,
+    /// Connection pool of the client forwarding requests upstream.
+    #[garde(dive)]
+    #[serde(default)]
+    pub client: client::Configuration,
+    /// Timeouts of forwarded requests, upstreams may override them.
+    #[garde(dive)]
+    #[serde(default)]
+    pub timeouts: timeouts::Configuration,
+    /// Retries of idempotent forwarded requests, upstreams may override them.
+    #[garde(dive)]
+    #[serde(default)]
+    pub retry: retry::Configuration,
+    /// Terminates TLS on `port` when set.
+    #[garde(dive)]
+    #[serde(default)]
+    pub tls: Option<tls::Configuration>,
 }
 
 impl Default for Configuration {
     fn default() -> Self {
-        Self { port: 80 }
+        Self {
+            port: 80,
+            upstreams: Vec::new(),
+            client: Default::default(),
+            timeouts: Default::default(),
+            retry: Default::default(),
+            tls: None,
+        }
     }
 }
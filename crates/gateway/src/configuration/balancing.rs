@@ -0,0 +1,25 @@
+/// How an upstream spreads requests over its endpoints.
+#[derive(Debug, Clone, Default, serde::Deserialize, garde::Validate)]
+#[serde(rename_all = "snake_case")]
+pub enum Configuration {
+    /// Takes the endpoints in turn, proportional to their weight.
+    #[default]
+    RoundRobin,
+    /// Picks the endpoint with the fewest requests in flight relative to its
+    /// weight.
+    LeastOutstanding,
+    /// Sends requests with the same key to the same endpoint, requests
+    /// without the key are spread round-robin.
+    ConsistentHash(#[garde(dive)] HashKey),
+}
+
+#[derive(Debug, Clone, serde::Deserialize, garde::Validate)]
+#[serde(rename_all = "snake_case")]
+pub enum HashKey {
+    /// Value of the request header with this name.
+    Header(#[garde(length(min = 1))] String),
+    /// Value of the cookie with this name.
+    Cookie(#[garde(length(min = 1))] String),
+    /// Address of the client.
+    Ip,
+}
@@ -0,0 +1,46 @@
+#[derive(Debug, Clone, serde::Deserialize, garde::Validate)]
+pub struct Configuration {
+    /// Connections kept open across all upstream hosts.
+    #[garde(range(min = 1))]
+    #[serde(default = "default_pool_size")]
+    pub pool_size: usize,
+    /// Idle connections are closed after this time.
+    #[garde(range(min = 1))]
+    #[serde(default = "default_idle_timeout_ms")]
+    pub idle_timeout_ms: u64,
+    /// Requests in flight to a single host, further requests wait.
+    #[garde(range(min = 1))]
+    #[serde(default = "default_max_requests_per_host")]
+    pub max_requests_per_host: usize,
+    /// Longest a request waits for a free request slot or pooled connection.
+    #[garde(range(min = 1))]
+    #[serde(default = "default_pool_timeout_ms")]
+    pub pool_timeout_ms: u64,
+}
+
+impl Default for Configuration {
+    fn default() -> Self {
+        Self {
+            pool_size: default_pool_size(),
+            idle_timeout_ms: default_idle_timeout_ms(),
+            max_requests_per_host: default_max_requests_per_host(),
+            pool_timeout_ms: default_pool_timeout_ms(),
+        }
+    }
+}
+
+fn default_pool_size() -> usize {
+    512
+}
+
+fn default_idle_timeout_ms() -> u64 {
+    90_000
+}
+
+fn default_max_requests_per_host() -> usize {
+    64
+}
+
+fn default_pool_timeout_ms() -> u64 {
+    5000
+}
@@ -0,0 +1,85 @@
+use super::validation;
+
+/// Probes the endpoints of an upstream, unhealthy ones receive no requests
+/// until they pass again.
+#[derive(Debug, Clone, serde::Deserialize, garde::Validate)]
+pub struct Configuration {
+    /// Written as a map like `http: {path: /healthz}`, YAML would otherwise
+    /// expect a tag for the probe.
+    #[garde(dive)]
+    #[serde(default, with = "serde_yaml::with::singleton_map")]
+    pub probe: Probe,
+    #[garde(range(min = 1))]
+    #[serde(default = "default_interval_ms")]
+    pub interval_ms: u64,
+    /// A probe taking longer fails.
+    #[garde(range(min = 1))]
+    #[serde(default = "default_timeout_ms")]
+    pub timeout_ms: u64,
+    /// Consecutive passed probes after which an unhealthy endpoint is healthy.
+    #[garde(range(min = 1))]
+    #[serde(default = "default_healthy_threshold")]
+    pub healthy_threshold: u32,
+    /// Consecutive failed probes after which a healthy endpoint is unhealthy.
+    #[garde(range(min = 1))]
+    #[serde(default = "default_unhealthy_threshold")]
+    pub unhealthy_threshold: u32,
+}
+
+#[derive(Debug, Clone, serde::Deserialize, garde::Validate)]
+#[serde(rename_all = "lowercase")]
+pub enum Probe {
+    /// Passes when a request to `path` answers with an expected status.
+    Http(#[garde(dive)] Http),
+    /// Passes when a connection can be opened.
+    Tcp,
+}
+
+impl Default for Probe {
+    fn default() -> Self {
+        Probe::Http(Http::default())
+    }
+}
+
+#[derive(Debug, Clone, serde::Deserialize, garde::Validate)]
+pub struct Http {
+    #[garde(custom(validation::is_valid_path))]
+    #[serde(default = "default_path")]
+    pub path: String,
+    #[garde(length(min = 1), inner(range(min = 100, max = 599)))]
+    #[serde(default = "default_expected_status")]
+    pub expected_status: Vec<u16>,
+}
+
+impl Default for Http {
+    fn default() -> Self {
+        Self {
+            path: default_path(),
+            expected_status: default_expected_status(),
+        }
+    }
+}
+
+fn default_interval_ms() -> u64 {
+    5_000
+}
+
+fn default_timeout_ms() -> u64 {
+    1_000
+}
+
+fn default_healthy_threshold() -> u32 {
+    2
+}
+
+fn default_unhealthy_threshold() -> u32 {
+    3
+}
+
+fn default_path() -> String {
+    "/".to_string()
+}
+
+fn default_expected_status() -> Vec<u16> {
+    vec![200]
+}
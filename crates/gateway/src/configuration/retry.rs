@@ -0,0 +1,60 @@
+#[derive(Debug, Clone, serde::Deserialize, garde::Validate)]
+pub struct Configuration {
+    /// Attempts of a request including the first one, `1` disables retries.
+    /// Only requests with an idempotent method and without a body are
+    /// retried, and only when no response arrived because connecting failed,
+    /// the connection closed or a timeout elapsed. Error statuses of the
+    /// upstream are passed on.
+    #[garde(range(min = 1))]
+    #[serde(default = "default_max_attempts")]
+    pub max_attempts: u32,
+    /// Backoff before the first retry, doubled for every further one.
+    #[garde(range(min = 1))]
+    #[serde(default = "default_initial_backoff_ms")]
+    pub initial_backoff_ms: u64,
+    #[garde(range(min = 1))]
+    #[serde(default = "default_max_backoff_ms")]
+    pub max_backoff_ms: u64,
+    /// Retries allowed on top of the requests of the last ten seconds, as a
+    /// fraction of those requests.
+    #[garde(range(min = 0.0, max = 1.0))]
+    #[serde(default = "default_budget_ratio")]
+    pub budget_ratio: f64,
+    /// Retries per second allowed regardless of the ratio, so upstreams with
+    /// little traffic can still be retried.
+    #[garde(skip)]
+    #[serde(default = "default_min_retries_per_second")]
+    pub min_retries_per_second: u32,
+}
+
+impl Default for Configuration {
+    fn default() -> Self {
+        Self {
+            max_attempts: default_max_attempts(),
+            initial_backoff_ms: default_initial_backoff_ms(),
+            max_backoff_ms: default_max_backoff_ms(),
+            budget_ratio: default_budget_ratio(),
+            min_retries_per_second: default_min_retries_per_second(),
+        }
+    }
+}
+
+fn default_max_attempts() -> u32 {
+    3
+}
+
+fn default_initial_backoff_ms() -> u64 {
+    25
+}
+
+fn default_max_backoff_ms() -> u64 {
+    1_000
+}
+
+fn default_budget_ratio() -> f64 {
+    0.2
+}
+
+fn default_min_retries_per_second() -> u32 {
+    10
+}
@@ -0,0 +1,38 @@
+#[derive(Debug, Clone, serde::Deserialize, garde::Validate)]
+pub struct Configuration {
+    /// Time to establish a connection to the upstream.
+    #[garde(range(min = 1))]
+    #[serde(default = "default_connect_timeout_ms")]
+    pub connect_timeout_ms: u64,
+    /// Time a single attempt may take until the response headers arrive.
+    #[garde(range(min = 1))]
+    #[serde(default = "default_read_timeout_ms")]
+    pub read_timeout_ms: u64,
+    /// Time from forwarding a request until its response body is complete,
+    /// covering all attempts, their backoff and the response hook.
+    #[garde(range(min = 1))]
+    #[serde(default = "default_total_timeout_ms")]
+    pub total_timeout_ms: u64,
+}
+
+impl Default for Configuration {
+    fn default() -> Self {
+        Self {
+            connect_timeout_ms: default_connect_timeout_ms(),
+            read_timeout_ms: default_read_timeout_ms(),
+            total_timeout_ms: default_total_timeout_ms(),
+        }
+    }
+}
+
+fn default_connect_timeout_ms() -> u64 {
+    5_000
+}
+
+fn default_read_timeout_ms() -> u64 {
+    30_000
+}
+
+fn default_total_timeout_ms() -> u64 {
+    60_000
+}
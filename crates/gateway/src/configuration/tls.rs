@@ -0,0 +1,39 @@
+use std::path::PathBuf;
+
+#[derive(Debug, Clone, serde::Deserialize, garde::Validate)]
+pub struct Configuration {
+    #[garde(length(min = 1), dive)]
+    pub certificates: Vec<Certificate>,
+    /// Interval in which certificate and key files are checked for changes.
+    #[garde(range(min = 1))]
+    #[serde(default = "default_reload_interval_ms")]
+    pub reload_interval_ms: u64,
+    /// Time a client gets to complete the handshake before it is dropped.
+    #[garde(range(min = 1))]
+    #[serde(default = "default_handshake_timeout_ms")]
+    pub handshake_timeout_ms: u64,
+}
+
+#[derive(Debug, Clone, serde::Deserialize, garde::Validate)]
+pub struct Certificate {
+    /// Names the certificate is served for, `*.example.com` matches a single
+    /// label. Without names it is served to clients no other certificate
+    /// matches.
+    #[garde(skip)]
+    #[serde(default)]
+    pub server_names: Vec<String>,
+    /// PEM encoded certificate chain.
+    #[garde(skip)]
+    pub certificate: PathBuf,
+    /// PEM encoded private key.
+    #[garde(skip)]
+    pub key: PathBuf,
+}
+
+fn default_reload_interval_ms() -> u64 {
+    5000
+}
+
+fn default_handshake_timeout_ms() -> u64 {
+    10_000
+}
@@ -0,0 +1,60 @@
+use super::{balancing, health_check, retry, timeouts, validation};
+
+#[derive(Debug, serde::Deserialize, garde::Validate)]
+pub struct Configuration {
+    #[garde(length(min = 1))]
+    pub name: String,
+    #[garde(length(min = 1), dive)]
+    pub endpoints: Vec<Endpoint>,
+    /// Written as a map like `consistent_hash: {header: x-user}`, YAML would
+    /// otherwise expect tags for the strategy and its key.
+    #[garde(dive)]
+    #[serde(default, with = "serde_yaml::with::singleton_map_recursive")]
+    pub balancing: balancing::Configuration,
+    /// Endpoints are assumed healthy when unset.
+    #[garde(dive)]
+    #[serde(default)]
+    pub health_check: Option<health_check::Configuration>,
+    /// Replaces the global timeouts for this upstream.
+    #[garde(dive)]
+    #[serde(default)]
+    pub timeouts: Option<timeouts::Configuration>,
+    /// Replaces the global retry policy for this upstream.
+    #[garde(dive)]
+    #[serde(default)]
+    pub retry: Option<retry::Configuration>,
+}
+
+/// An endpoint given as plain address has a weight of one.
+#[derive(Debug, Clone, serde::Deserialize, garde::Validate)]
+#[serde(untagged)]
+pub enum Endpoint {
+    Address(#[garde(custom(validation::is_valid_endpoint))] String),
+    Weighted {
+        #[garde(custom(validation::is_valid_endpoint))]
+        address: String,
+        #[garde(range(min = 1, max = 1000))]
+        #[serde(default = "default_weight")]
+        weight: u32,
+    },
+}
+
+impl Endpoint {
+    pub fn address(&self) -> &str {
+        match self {
+            Endpoint::Address(address) => address,
+            Endpoint::Weighted { address, .. } => address,
+        }
+    }
+
+    pub fn weight(&self) -> u32 {
+        match self {
+            Endpoint::Address(_) => default_weight(),
+            Endpoint::Weighted { weight, .. } => *weight,
+        }
+    }
+}
+
+fn default_weight() -> u32 {
+    1
+}
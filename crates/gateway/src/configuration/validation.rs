@@ -12,3 +12,20 @@ pub(super) fn is_valid_port(value: &u16, _: &()) -> garde::Result {
         }
     }
 }
+
+pub(super) fn is_valid_endpoint(value: &str, _: &()) -> garde::Result {
+    match crate::upstream::Endpoint::parse(value) {
+        Ok(_) => Ok(()),
+        Err(error) => Err(garde::Error::new(error.to_string())),
+    }
+}
+
+pub(super) fn is_valid_path(value: &str, _: &()) -> garde::Result {
+    match value.starts_with('/') {
+        true => Ok(()),
+        false => Err(garde::Error::new(format!(
+            "Path {} has to start with /",
+            value
+        ))),
+    }
+}
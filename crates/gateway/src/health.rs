@@ -0,0 +1,186 @@
+use anyhow::{Result, anyhow, bail};
+use axum::Json;
+use axum::extract::State;
+use rama::http::dep::http_body_util::BodyExt;
+use rama::http::{Body, Request};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use std::time::Duration;
+use tokio::net::TcpStream;
+use tokio::task::JoinSet;
+
+use crate::client::UpstreamClient;
+use crate::configuration::health_check::{Configuration, Probe};
+use crate::configuration::timeouts;
+use crate::upstream::{Endpoint, Upstreams};
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct UpstreamHealth {
+    pub name: String,
+    pub endpoints: Vec<EndpointHealth>,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct EndpointHealth {
+    pub endpoint: String,
+    pub status: Status,
+    /// Requests currently in flight to the endpoint.
+    pub outstanding: usize,
+    /// Reason the last probe failed, cleared once a probe passes.
+    pub last_error: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    Healthy,
+    Unhealthy,
+    /// The upstream has no health check.
+    Unchecked,
+}
+
+/// Health of an endpoint as seen by its probes, endpoints start out healthy
+/// so they serve requests before the first probe.
+#[derive(Debug)]
+pub(crate) struct Health {
+    healthy: AtomicBool,
+    streak: Mutex<Streak>,
+}
+
+#[derive(Debug, Default)]
+struct Streak {
+    passed: u32,
+    failed: u32,
+    last_error: Option<String>,
+}
+
+impl Default for Health {
+    fn default() -> Self {
+        Self {
+            healthy: AtomicBool::new(true),
+            streak: Default::default(),
+        }
+    }
+}
+
+impl Health {
+    pub(crate) fn is_healthy(&self) -> bool {
+        self.healthy.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn last_error(&self) -> Option<String> {
+        self.streak
+            .lock()
+            .ok()
+            .and_then(|streak| streak.last_error.clone())
+    }
+
+    /// Flips the endpoint once enough probes in a row agree.
+    fn record(&self, endpoint: &Endpoint, result: Result<()>, configuration: &Configuration) {
+        let Ok(mut streak) = self.streak.lock() else {
+            return;
+        };
+        match result {
+            Ok(()) => {
+                streak.failed = 0;
+                streak.passed = streak.passed.saturating_add(1);
+                streak.last_error = None;
+                if !self.is_healthy() && streak.passed >= configuration.healthy_threshold {
+                    self.healthy.store(true, Ordering::Relaxed);
+                    tracing::info!(%endpoint, "Endpoint is healthy again");
+                }
+            }
+            Err(error) => {
+                streak.passed = 0;
+                streak.failed = streak.failed.saturating_add(1);
+                streak.last_error = Some(error.to_string());
+                if self.is_healthy() && streak.failed >= configuration.unhealthy_threshold {
+                    self.healthy.store(false, Ordering::Relaxed);
+                    tracing::warn!(%endpoint, %error, "Endpoint is unhealthy");
+                }
+            }
+        }
+    }
+}
+
+/// Probes the endpoints of every upstream with a health check, probes use a
+/// client of their own so they do not wait behind forwarded requests.
+pub(crate) fn spawn_checks(upstreams: &Arc<Upstreams>) -> Result<()> {
+    let client = UpstreamClient::new(&Default::default())?;
+    for (name, configuration) in upstreams.health_checks() {
+        tokio::spawn(check(
+            Arc::downgrade(upstreams),
+            name,
+            configuration,
+            client.clone(),
+        ));
+    }
+    Ok(())
+}
+
+async fn check(
+    upstreams: Weak<Upstreams>,
+    name: String,
+    configuration: Configuration,
+    client: UpstreamClient,
+) {
+    let mut interval = tokio::time::interval(Duration::from_millis(configuration.interval_ms));
+    loop {
+        interval.tick().await;
+        let Some(upstreams) = upstreams.upgrade() else {
+            return;
+        };
+        let mut probes = JoinSet::new();
+        for (endpoint, health) in upstreams.members(&name) {
+            let configuration = configuration.clone();
+            let client = client.clone();
+            probes.spawn(async move {
+                let result = probe(&endpoint, &configuration, &client).await;
+                health.record(&endpoint, result, &configuration);
+            });
+        }
+        drop(upstreams);
+        probes.join_all().await;
+    }
+}
+
+async fn probe(
+    endpoint: &Endpoint,
+    configuration: &Configuration,
+    client: &UpstreamClient,
+) -> Result<()> {
+    let probe = async {
+        match &configuration.probe {
+            Probe::Tcp => {
+                TcpStream::connect(endpoint.socket_address()).await?;
+                Ok::<_, anyhow::Error>(())
+            }
+            Probe::Http(http) => {
+                let mut request = Request::builder()
+                    .uri(http.path.as_str())
+                    .body(Body::empty())?;
+                endpoint.route(&mut request)?;
+                let timeouts = timeouts::Configuration {
+                    connect_timeout_ms: configuration.timeout_ms,
+                    read_timeout_ms: configuration.timeout_ms,
+                    total_timeout_ms: configuration.timeout_ms,
+                };
+                let response = client.send(request, &timeouts).await?;
+                let status = response.status().as_u16();
+                // Reading the body lets the connection be reused.
+                let _ = response.into_body().collect().await;
+                if !http.expected_status.contains(&status) {
+                    bail!("Unexpected status {}", status);
+                }
+                Ok(())
+            }
+        }
+    };
+    tokio::time::timeout(Duration::from_millis(configuration.timeout_ms), probe)
+        .await
+        .map_err(|_| anyhow!("Probe timed out"))?
+}
+
+pub(crate) async fn report(State(upstreams): State<Arc<Upstreams>>) -> Json<Vec<UpstreamHealth>> {
+    Json(upstreams.health())
+}
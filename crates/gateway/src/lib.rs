@@ -1,33 +1,93 @@
+pub mod balancer;
+pub mod client;
 pub mod configuration;
+pub mod health;
 mod proxy;
+pub mod retry;
+pub mod tls;
+mod upstream;
 
 use anyhow::{Error, Result};
+use axum::Router;
+use axum::routing::get;
 use rama::{http::server::HttpServer, rt::Executor};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 
+use client::UpstreamClient;
 use configuration::Configuration;
+use tls::CertificateStore;
+use upstream::Upstreams;
 
 use runtime::Runtime;
 
 pub struct Gateway {
     port: u16,
+    upstreams: Arc<Upstreams>,
+    client: UpstreamClient,
+    certificates: Option<Arc<CertificateStore>>,
+    checking_health: AtomicBool,
 }
 
 impl Gateway {
     pub fn new(configuration: &Configuration) -> Result<Self> {
         let gateway = Self {
             port: configuration.port,
+            upstreams: Arc::new(Upstreams::new(
+                &configuration.upstreams,
+                &configuration.timeouts,
+                &configuration.retry,
+            )?),
+            client: UpstreamClient::new(&configuration.client)?,
+            certificates: configuration
+                .tls
+                .as_ref()
+                .map(|tls| CertificateStore::new(tls).map(Arc::new))
+                .transpose()?,
+            checking_health: AtomicBool::new(false),
         };
         Ok(gateway)
     }
 
+    /// Routes the admin API serves next to its own, reporting the health of
+    /// upstream endpoints.
+    pub fn admin_routes(&self) -> Router {
+        Router::new()
+            .route("/upstreams/health", get(health::report))
+            .with_state(self.upstreams.clone())
+    }
+
+    pub fn upstream_health(&self) -> Vec<health::UpstreamHealth> {
+        self.upstreams.health()
+    }
+
+    /// Starts probing the upstreams that have a health check, only the first
+    /// call does so.
+    pub fn check_health(&self) -> Result<()> {
+        if self.checking_health.swap(true, Ordering::Relaxed) {
+            return Ok(());
+        }
+        health::spawn_checks(&self.upstreams)
+    }
+
     pub async fn run(&self, runtime: Runtime) -> Result<()> {
+        self.check_health()?;
         let executor = Executor::default();
         let address = ([0, 0, 0, 0], self.port);
-        
-        let proxy = proxy::WebAssemblyComponentProxy::new(runtime);
-        HttpServer::auto(executor)
-            .listen(address, proxy)
-            .await
-            .map_err(|e| Error::from_boxed(e))
+
+        let proxy = proxy::WebAssemblyComponentProxy::new(
+            runtime,
+            self.upstreams.clone(),
+            self.client.clone(),
+        );
+        let Some(certificates) = &self.certificates else {
+            return HttpServer::auto(executor)
+                .listen(address, proxy)
+                .await
+                .map_err(|e| Error::from_boxed(e));
+        };
+        certificates.spawn_reloader();
+        let service = HttpServer::auto(executor).service(proxy);
+        tls::serve(address.into(), certificates, service).await
     }
 }
@@ -1,22 +1,189 @@
 use anyhow::Result;
-use rama::http::client::EasyHttpWebClient;
-use rama::http::{Body, Request, Response, StatusCode};
+use bytes::Bytes;
+use rama::error::BoxError;
+use rama::http::dep::http_body::{self, Body as _, Frame, SizeHint};
+use rama::http::{Body, HeaderMap, Method, Request, Response, StatusCode, Uri, Version};
+use rama::net::stream::SocketInfo;
 use rama::{Context, Service};
-use runtime::resolution::Resolution;
+use runtime::resolution::{Forward, Resolution, ResponseHook, Target};
+use std::net::IpAddr;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context as TaskContext, Poll};
+use std::time::Duration;
+use tokio::time::{Instant, Sleep};
 
-use runtime::Runtime;
+use crate::balancer::Outstanding;
+use crate::client::{self, UpstreamClient};
+use crate::retry::Policy;
+use crate::upstream::Upstreams;
+use runtime::{Error, Runtime};
 
 #[derive(Clone)]
 pub struct WebAssemblyComponentProxy {
     runtime: Runtime,
+    upstreams: Arc<Upstreams>,
+    client: UpstreamClient,
 }
 
 impl WebAssemblyComponentProxy {
-    pub fn new(runtime: Runtime) -> Self {
-        Self { runtime }
+    pub fn new(runtime: Runtime, upstreams: Arc<Upstreams>, client: UpstreamClient) -> Self {
+        Self {
+            runtime,
+            upstreams,
+            client,
+        }
+    }
+
+    /// Sends the request upstream and runs the response hook within the
+    /// total timeout of its target, which also bounds reading the response
+    /// body. Failures are returned as the response to send to the client.
+    async fn forward(
+        &self,
+        request: Request,
+        target: Option<&Target>,
+        response_hook: Option<Box<ResponseHook>>,
+        client: Option<IpAddr>,
+    ) -> Result<Response, Response> {
+        let policy = self.upstreams.policy(target);
+        let deadline = Instant::now() + Duration::from_millis(policy.timeouts.total_timeout_ms);
+        let forward = async {
+            let response = self.attempts(request, target, client, &policy).await?;
+            match response_hook {
+                Some(response_hook) => response_hook.process(response).await.map_err(runtime_error),
+                None => Ok(response),
+            }
+        };
+        let response = tokio::time::timeout_at(deadline, forward)
+            .await
+            .map_err(|_| gateway_timeout())??;
+        Ok(until_deadline(response, deadline))
+    }
+
+    /// Failed attempts are retried while the budget allows it, as long as
+    /// the request can be replayed.
+    async fn attempts(
+        &self,
+        mut request: Request,
+        target: Option<&Target>,
+        client: Option<IpAddr>,
+        policy: &Policy,
+    ) -> Result<Response, Response> {
+        policy.record_request();
+        let replay = replayable(&request);
+        let mut retries = 0;
+        loop {
+            let mut outstanding = None;
+            if let Some(target) = target {
+                outstanding = self
+                    .upstreams
+                    .resolve(target, &request, client)
+                    .and_then(|(endpoint, outstanding)| {
+                        endpoint.route(&mut request)?;
+                        Ok(outstanding)
+                    })
+                    .map_err(|e| bad_gateway(format!("Failed to route to upstream: {}", e)))?;
+            }
+            let error = match self.client.send(request, &policy.timeouts).await {
+                Ok(response) => return Ok(in_flight_until_done(response, outstanding)),
+                Err(error) => error,
+            };
+            let next = replay
+                .as_ref()
+                .filter(|_| retries + 1 < policy.retry.max_attempts && policy.try_retry());
+            let Some(replay) = next else {
+                return Err(upstream_error(error));
+            };
+            retries += 1;
+            tracing::debug!(%error, retries, "Retrying upstream request");
+            tokio::time::sleep(policy.backoff(retries)).await;
+            request = replay.clone_empty();
+        }
     }
 }
 
+/// Keeps the request counted as in flight to its endpoint until the response
+/// body is dropped.
+fn in_flight_until_done(response: Response, outstanding: Option<Outstanding>) -> Response {
+    match outstanding {
+        Some(outstanding) => client::hold_until_dropped(response, outstanding),
+        None => response,
+    }
+}
+
+/// Fails the response body once the deadline passed, a body still streaming
+/// then is cut off.
+fn until_deadline(response: Response, deadline: Instant) -> Response {
+    response.map(|body| {
+        Body::new(Deadline {
+            body,
+            sleep: Box::pin(tokio::time::sleep_until(deadline)),
+        })
+    })
+}
+
+struct Deadline {
+    body: Body,
+    sleep: Pin<Box<Sleep>>,
+}
+
+impl http_body::Body for Deadline {
+    type Data = Bytes;
+    type Error = BoxError;
+
+    fn poll_frame(
+        mut self: Pin<&mut Self>,
+        cx: &mut TaskContext<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        if self.sleep.as_mut().poll(cx).is_ready() {
+            return Poll::Ready(Some(Err("Total timeout elapsed".into())));
+        }
+        Pin::new(&mut self.body).poll_frame(cx).map_err(Into::into)
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.body.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.body.size_hint()
+    }
+}
+
+/// Head of a request without body, only those with idempotent methods are
+/// replayed.
+struct Replay {
+    method: Method,
+    uri: Uri,
+    version: Version,
+    headers: HeaderMap,
+}
+
+impl Replay {
+    fn clone_empty(&self) -> Request {
+        let mut request = Request::new(Body::empty());
+        *request.method_mut() = self.method.clone();
+        *request.uri_mut() = self.uri.clone();
+        *request.version_mut() = self.version;
+        *request.headers_mut() = self.headers.clone();
+        request
+    }
+}
+
+fn replayable(request: &Request) -> Option<Replay> {
+    let idempotent = matches!(
+        *request.method(),
+        Method::GET | Method::HEAD | Method::OPTIONS | Method::TRACE | Method::PUT | Method::DELETE
+    );
+    let empty = request.body().size_hint().exact() == Some(0);
+    (idempotent && empty).then(|| Replay {
+        method: request.method().clone(),
+        uri: request.uri().clone(),
+        version: request.version(),
+        headers: request.headers().clone(),
+    })
+}
+
 impl<State> Service<State, Request> for WebAssemblyComponentProxy
 where
     State: Send + Sync + 'static,
@@ -26,34 +193,68 @@ where
 
     async fn serve(
         &self,
-        _context: Context<State>,
+        context: Context<State>,
         request: Request,
     ) -> Result<Self::Response, Self::Error> {
-        match self.runtime.process(request) {
+        match self.runtime.process(request).await {
             Ok(resolution) => match resolution {
-                Resolution::Forward(request) => {
-                    let client = EasyHttpWebClient::default();
-                    match client.serve(Context::default(), request).await {
-                        Ok(response) => Ok(response),
-                        Err(error) => {
-                            let error_message =
-                                format!("Failed to connect to destination: {}", error.to_string());
-                            Ok(Response::builder()
-                                .status(StatusCode::BAD_GATEWAY)
-                                .body(error_message.into())
-                                .unwrap())
-                        }
-                    }
+                Resolution::Forward(Forward {
+                    request,
+                    target,
+                    response_hook,
+                }) => {
+                    let client = context
+                        .get::<SocketInfo>()
+                        .map(|socket| socket.peer_addr().ip());
+                    let response = self
+                        .forward(request, target.as_ref(), response_hook, client)
+                        .await;
+                    Ok(response.unwrap_or_else(|response| response))
                 }
                 Resolution::Respond(response) => Ok(response),
             },
-            Err(e) => {
-                let response = Response::builder()
-                    .status(StatusCode::INTERNAL_SERVER_ERROR)
-                    .body(Body::from(format!("Internal Server Error: {}", e)))
-                    .unwrap();
-                Ok(response)
-            }
+            Err(e) => Ok(runtime_error(e)),
         }
     }
 }
+
+fn bad_gateway(error_message: String) -> Response {
+    Response::builder()
+        .status(StatusCode::BAD_GATEWAY)
+        .body(error_message.into())
+        .unwrap()
+}
+
+fn gateway_timeout() -> Response {
+    Response::builder()
+        .status(StatusCode::GATEWAY_TIMEOUT)
+        .body(Body::from(StatusCode::GATEWAY_TIMEOUT.to_string()))
+        .unwrap()
+}
+
+/// Connection failures are logged, clients only see the status.
+fn upstream_error(error: client::Error) -> Response {
+    match error {
+        client::Error::Timeout => gateway_timeout(),
+        client::Error::Failed(error) => {
+            tracing::warn!(%error, "Failed to forward request upstream");
+            Response::builder()
+                .status(StatusCode::BAD_GATEWAY)
+                .body(Body::from(StatusCode::BAD_GATEWAY.to_string()))
+                .unwrap()
+        }
+    }
+}
+
+/// Details of the failure are logged, clients only see the status.
+fn runtime_error(error: Error) -> Response {
+    let status = match error {
+        Error::Timeout => StatusCode::SERVICE_UNAVAILABLE,
+        Error::MemoryLimit(_) | Error::Failed(_) => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+    tracing::error!(%error, "Component failed");
+    Response::builder()
+        .status(status)
+        .body(Body::from(status.to_string()))
+        .unwrap()
+}
@@ -0,0 +1,78 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::configuration::{retry, timeouts};
+
+/// Period over which the retry budget counts requests and retries.
+const BUDGET_WINDOW: Duration = Duration::from_secs(10);
+
+/// Timeouts and retries of requests to one upstream, the budget is shared by
+/// all of its requests.
+pub struct Policy {
+    pub timeouts: timeouts::Configuration,
+    pub retry: retry::Configuration,
+    budget: Mutex<Budget>,
+}
+
+struct Budget {
+    started_at: Instant,
+    requests: u64,
+    retries: u64,
+}
+
+impl Policy {
+    pub fn new(timeouts: timeouts::Configuration, retry: retry::Configuration) -> Self {
+        Self {
+            timeouts,
+            retry,
+            budget: Mutex::new(Budget {
+                started_at: Instant::now(),
+                requests: 0,
+                retries: 0,
+            }),
+        }
+    }
+
+    pub fn record_request(&self) {
+        if let Ok(mut budget) = self.budget.lock() {
+            budget.reset_if_elapsed();
+            budget.requests += 1;
+        }
+    }
+
+    /// Takes a retry from the budget, `false` if it is exhausted.
+    pub fn try_retry(&self) -> bool {
+        let Ok(mut budget) = self.budget.lock() else {
+            return false;
+        };
+        budget.reset_if_elapsed();
+        let allowed = self.retry.min_retries_per_second as f64 * BUDGET_WINDOW.as_secs_f64()
+            + self.retry.budget_ratio * budget.requests as f64;
+        if budget.retries as f64 >= allowed {
+            return false;
+        }
+        budget.retries += 1;
+        true
+    }
+
+    /// Exponential backoff with full jitter before retry number `retry`,
+    /// starting at one.
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let ceiling = self
+            .retry
+            .initial_backoff_ms
+            .saturating_mul(1 << (retry - 1).min(16))
+            .min(self.retry.max_backoff_ms);
+        Duration::from_millis(rand::random_range(0..=ceiling))
+    }
+}
+
+impl Budget {
+    fn reset_if_elapsed(&mut self) {
+        if self.started_at.elapsed() >= BUDGET_WINDOW {
+            self.started_at = Instant::now();
+            self.requests = 0;
+            self.retries = 0;
+        }
+    }
+}
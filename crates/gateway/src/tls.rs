@@ -0,0 +1,226 @@
+use anyhow::{Context as _, Result, anyhow};
+use rama::net::stream::SocketInfo;
+use rama::{Context, Service};
+use rustls::ServerConfig;
+use rustls::crypto::aws_lc_rs::default_provider;
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use rustls::server::{ClientHello, ResolvesServerCert};
+use rustls::sign::CertifiedKey;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, SystemTime};
+use tokio::net::{TcpListener, TcpStream};
+use tokio_rustls::TlsAcceptor;
+use tokio_rustls::server::TlsStream;
+
+use crate::configuration::tls::{Certificate, Configuration};
+
+/// Bounds of the pause after a failed accept, it doubles while they repeat.
+const MIN_ACCEPT_BACKOFF: Duration = Duration::from_millis(5);
+const MAX_ACCEPT_BACKOFF: Duration = Duration::from_secs(1);
+
+/// Certificates chosen by SNI, swapped in place when their files change.
+#[derive(Debug)]
+pub struct CertificateStore {
+    configuration: Configuration,
+    certificates: RwLock<Certificates>,
+    modified: Mutex<Vec<Option<SystemTime>>>,
+}
+
+#[derive(Debug, Default)]
+struct Certificates {
+    by_name: HashMap<String, Arc<CertifiedKey>>,
+    default: Option<Arc<CertifiedKey>>,
+}
+
+impl CertificateStore {
+    pub fn new(configuration: &Configuration) -> Result<Self> {
+        let store = Self {
+            configuration: configuration.clone(),
+            certificates: RwLock::new(Certificates::load(&configuration.certificates)?),
+            modified: Mutex::new(modification_times(&configuration.certificates)),
+        };
+        Ok(store)
+    }
+
+    fn server_config(self: &Arc<Self>) -> Result<Arc<ServerConfig>> {
+        let mut config = ServerConfig::builder_with_provider(default_provider().into())
+            .with_safe_default_protocol_versions()?
+            .with_no_client_auth()
+            .with_cert_resolver(self.clone());
+        config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+        Ok(Arc::new(config))
+    }
+
+    /// Certificate served to clients asking for `server_name`.
+    pub fn certificate_for(&self, server_name: Option<&str>) -> Option<Arc<CertifiedKey>> {
+        self.certificates.read().ok()?.resolve(server_name)
+    }
+
+    /// Polls the certificate and key files, a set that fails to load keeps
+    /// the previous certificates in place.
+    pub(crate) fn spawn_reloader(self: &Arc<Self>) {
+        let store = Arc::downgrade(self);
+        let interval = Duration::from_millis(self.configuration.reload_interval_ms);
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(interval);
+            loop {
+                interval.tick().await;
+                let Some(store) = store.upgrade() else {
+                    return;
+                };
+                if let Err(error) = store.reload_if_changed() {
+                    tracing::error!(%error, "Failed to reload certificates");
+                }
+            }
+        });
+    }
+
+    /// Loads the certificates again if one of their files changed since the
+    /// last successful load.
+    pub fn reload_if_changed(&self) -> Result<()> {
+        let modified = modification_times(&self.configuration.certificates);
+        let mut previous = self
+            .modified
+            .lock()
+            .map_err(|e| anyhow!("Failed to acquire lock of modification times: {}", e))?;
+        if *previous == modified {
+            return Ok(());
+        }
+        let certificates = Certificates::load(&self.configuration.certificates)?;
+        *self
+            .certificates
+            .write()
+            .map_err(|e| anyhow!("Failed to acquire write lock of certificates: {}", e))? =
+            certificates;
+        *previous = modified;
+        tracing::info!("Reloaded certificates");
+        Ok(())
+    }
+}
+
+impl ResolvesServerCert for CertificateStore {
+    fn resolve(&self, client_hello: ClientHello<'_>) -> Option<Arc<CertifiedKey>> {
+        self.certificate_for(client_hello.server_name())
+    }
+}
+
+impl Certificates {
+    /// Exact names win over wildcards, clients without a matching name or
+    /// without SNI get the default certificate.
+    fn resolve(&self, server_name: Option<&str>) -> Option<Arc<CertifiedKey>> {
+        let Some(server_name) = server_name else {
+            return self.default.clone();
+        };
+        let server_name = server_name.to_ascii_lowercase();
+        let wildcard = server_name
+            .split_once('.')
+            .map(|(_, parent)| format!("*.{}", parent));
+        self.by_name
+            .get(&server_name)
+            .or_else(|| wildcard.and_then(|wildcard| self.by_name.get(&wildcard)))
+            .or(self.default.as_ref())
+            .cloned()
+    }
+
+    fn load(configurations: &[Certificate]) -> Result<Self> {
+        let mut certificates = Certificates::default();
+        for configuration in configurations {
+            let certified_key = Arc::new(certified_key(configuration)?);
+            if configuration.server_names.is_empty() {
+                certificates.default.get_or_insert(certified_key.clone());
+            }
+            for server_name in &configuration.server_names {
+                certificates
+                    .by_name
+                    .insert(server_name.to_ascii_lowercase(), certified_key.clone());
+            }
+        }
+        Ok(certificates)
+    }
+}
+
+fn certified_key(configuration: &Certificate) -> Result<CertifiedKey> {
+    let chain = CertificateDer::pem_file_iter(&configuration.certificate)
+        .and_then(|certificates| certificates.collect::<Result<Vec<_>, _>>())
+        .with_context(|| format!("Failed to read {:?}", configuration.certificate))?;
+    if chain.is_empty() {
+        return Err(anyhow!("No certificate in {:?}", configuration.certificate));
+    }
+    let key = PrivateKeyDer::from_pem_file(&configuration.key)
+        .with_context(|| format!("Failed to read {:?}", configuration.key))?;
+    // Also checks the key belongs to the certificate, so a certificate
+    // replaced before its key is not served with the old one.
+    CertifiedKey::from_der(chain, key, &default_provider()).with_context(|| {
+        format!(
+            "Invalid private key {:?} for {:?}",
+            configuration.key, configuration.certificate
+        )
+    })
+}
+
+fn modification_times(configurations: &[Certificate]) -> Vec<Option<SystemTime>> {
+    configurations
+        .iter()
+        .flat_map(|configuration| [&configuration.certificate, &configuration.key])
+        .map(|path| std::fs::metadata(path).and_then(|m| m.modified()).ok())
+        .collect()
+}
+
+/// Accepts TLS connections and hands them to the HTTP service, a failed or
+/// stalled handshake only drops its connection. Failing accepts, like running
+/// out of file descriptors, are retried after a pause.
+pub(crate) async fn serve<S>(
+    address: SocketAddr,
+    certificates: &Arc<CertificateStore>,
+    service: S,
+) -> Result<()>
+where
+    S: Service<(), TlsStream<TcpStream>>,
+    S::Error: std::fmt::Display,
+{
+    let handshake_timeout = Duration::from_millis(certificates.configuration.handshake_timeout_ms);
+    let listener = TcpListener::bind(address).await?;
+    let acceptor = TlsAcceptor::from(certificates.server_config()?);
+    let service = Arc::new(service);
+    let mut backoff = MIN_ACCEPT_BACKOFF;
+    loop {
+        let (stream, peer) = match listener.accept().await {
+            Ok(accepted) => {
+                backoff = MIN_ACCEPT_BACKOFF;
+                accepted
+            }
+            Err(error) => {
+                tracing::warn!(%error, ?backoff, "Failed to accept TLS connection");
+                tokio::time::sleep(backoff).await;
+                backoff = (backoff * 2).min(MAX_ACCEPT_BACKOFF);
+                continue;
+            }
+        };
+        let acceptor = acceptor.clone();
+        let service = service.clone();
+        tokio::spawn(async move {
+            let stream =
+                match tokio::time::timeout(handshake_timeout, acceptor.accept(stream)).await {
+                    Ok(Ok(stream)) => stream,
+                    Ok(Err(error)) => {
+                        tracing::debug!(%peer, %error, "TLS handshake failed");
+                        return;
+                    }
+                    Err(_) => {
+                        tracing::debug!(%peer, "TLS handshake timed out");
+                        return;
+                    }
+                };
+            // Like the plain listener, tell the service who it is talking to.
+            let local = stream.get_ref().0.local_addr().ok();
+            let mut context = Context::default();
+            context.insert(SocketInfo::new(local, peer));
+            if let Err(error) = service.serve(context, stream).await {
+                tracing::debug!(%peer, %error, "Connection failed");
+            }
+        });
+    }
+}
@@ -0,0 +1,230 @@
+use anyhow::{Result, anyhow, bail};
+use rama::http::header::HOST;
+use rama::http::{HeaderValue, Request, Uri};
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Arc;
+
+use crate::balancer::{Balancer, Outstanding};
+use crate::configuration::upstream::Configuration;
+use crate::configuration::{health_check, retry, timeouts};
+use crate::health::{EndpointHealth, Health, Status, UpstreamHealth};
+use crate::retry::Policy;
+use runtime::resolution::Target;
+
+/// Scheme and authority a forwarded request is dialed at.
+#[derive(Debug, Clone)]
+pub(crate) struct Endpoint {
+    uri: Uri,
+}
+
+impl Endpoint {
+    /// Parses `host:port` or `scheme://host:port`, defaulting to plain http.
+    /// Only `http` and `https` are accepted as schemes.
+    pub(crate) fn parse(value: &str) -> Result<Self> {
+        let value = if value.contains("://") {
+            value.to_string()
+        } else {
+            format!("http://{}", value)
+        };
+        let uri = value
+            .parse::<Uri>()
+            .map_err(|e| anyhow!("Invalid endpoint {}: {}", value, e))?;
+        if !matches!(uri.scheme_str(), Some("http" | "https")) {
+            bail!("Endpoint {} has to use http or https", value);
+        }
+        if uri.authority().is_none() {
+            bail!("Endpoint {} has no authority", value);
+        }
+        if uri.path_and_query().is_some_and(|p| p.as_str() != "/") {
+            bail!("Endpoint {} must not contain a path", value);
+        }
+        Ok(Self { uri })
+    }
+
+    /// Points the request at this endpoint, keeping path, query and Host.
+    pub(crate) fn route(&self, request: &mut Request) -> Result<()> {
+        if !request.headers().contains_key(HOST)
+            && let Some(authority) = request.uri().authority()
+        {
+            let host = HeaderValue::from_str(authority.as_str())?;
+            request.headers_mut().insert(HOST, host);
+        }
+        let path_and_query = request
+            .uri()
+            .path_and_query()
+            .map(|p| p.as_str())
+            .unwrap_or("/")
+            .to_string();
+        let mut builder = Uri::builder().path_and_query(path_and_query);
+        if let Some(scheme) = self.uri.scheme() {
+            builder = builder.scheme(scheme.clone());
+        }
+        if let Some(authority) = self.uri.authority() {
+            builder = builder.authority(authority.clone());
+        }
+        *request.uri_mut() = builder.build()?;
+        Ok(())
+    }
+
+    /// `host:port` to open connections to, the port defaults by scheme.
+    pub(crate) fn socket_address(&self) -> String {
+        let host = self.uri.host().unwrap_or_default();
+        let port = self.uri.port_u16().unwrap_or(match self.uri.scheme_str() {
+            Some("https") => 443,
+            _ => 80,
+        });
+        format!("{}:{}", host, port)
+    }
+
+    /// `scheme://host:port` with the port spelled out, so an endpoint hashes
+    /// the same whether or not its default port is written down.
+    pub(crate) fn key(&self) -> String {
+        format!(
+            "{}://{}",
+            self.uri.scheme_str().unwrap_or("http"),
+            self.socket_address()
+        )
+    }
+}
+
+impl std::fmt::Display for Endpoint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.uri)
+    }
+}
+
+struct Upstream {
+    balancer: Balancer,
+    health_check: Option<health_check::Configuration>,
+    policy: Arc<Policy>,
+}
+
+pub(crate) struct Upstreams {
+    upstreams: HashMap<String, Upstream>,
+    /// Applies to addresses and requests without a target.
+    default_policy: Arc<Policy>,
+}
+
+impl Upstreams {
+    pub(crate) fn new(
+        configurations: &[Configuration],
+        timeouts: &timeouts::Configuration,
+        retry: &retry::Configuration,
+    ) -> Result<Self> {
+        let mut upstreams = HashMap::with_capacity(configurations.len());
+        for configuration in configurations {
+            let endpoints = configuration
+                .endpoints
+                .iter()
+                .map(|endpoint| Ok((Endpoint::parse(endpoint.address())?, endpoint.weight())))
+                .collect::<Result<Vec<_>>>()?;
+            let policy = Policy::new(
+                configuration.timeouts.as_ref().unwrap_or(timeouts).clone(),
+                configuration.retry.as_ref().unwrap_or(retry).clone(),
+            );
+            let upstream = Upstream {
+                balancer: Balancer::new(endpoints, &configuration.balancing),
+                health_check: configuration.health_check.clone(),
+                policy: Arc::new(policy),
+            };
+            if upstreams
+                .insert(configuration.name.clone(), upstream)
+                .is_some()
+            {
+                bail!(
+                    "Upstream {} is configured more than once",
+                    configuration.name
+                );
+            }
+        }
+        Ok(Self {
+            upstreams,
+            default_policy: Arc::new(Policy::new(timeouts.clone(), retry.clone())),
+        })
+    }
+
+    pub(crate) fn policy(&self, target: Option<&Target>) -> Arc<Policy> {
+        match target {
+            Some(Target::Upstream(name)) => self
+                .upstreams
+                .get(name)
+                .map(|upstream| upstream.policy.clone())
+                .unwrap_or_else(|| self.default_policy.clone()),
+            _ => self.default_policy.clone(),
+        }
+    }
+
+    /// Endpoint of the target, upstreams count the request as in flight to
+    /// the picked endpoint while the returned guard is alive.
+    pub(crate) fn resolve(
+        &self,
+        target: &Target,
+        request: &Request,
+        client: Option<IpAddr>,
+    ) -> Result<(Endpoint, Option<Outstanding>)> {
+        match target {
+            Target::Address(address) => Ok((Endpoint::parse(address)?, None)),
+            Target::Upstream(name) => {
+                let upstream = self
+                    .upstreams
+                    .get(name)
+                    .ok_or_else(|| anyhow!("Unknown upstream {}", name))?;
+                let (endpoint, outstanding) = upstream
+                    .balancer
+                    .pick(request, client)
+                    .ok_or_else(|| anyhow!("No healthy endpoint in upstream {}", name))?;
+                Ok((endpoint, Some(outstanding)))
+            }
+        }
+    }
+
+    pub(crate) fn health_checks(&self) -> Vec<(String, health_check::Configuration)> {
+        self.upstreams
+            .iter()
+            .filter_map(|(name, upstream)| Some((name.clone(), upstream.health_check.clone()?)))
+            .collect()
+    }
+
+    /// Endpoints of the upstream with the health their probes report to.
+    pub(crate) fn members(&self, name: &str) -> Vec<(Endpoint, Arc<Health>)> {
+        self.upstreams
+            .get(name)
+            .map(|upstream| {
+                upstream
+                    .balancer
+                    .members()
+                    .iter()
+                    .map(|member| (member.endpoint.clone(), member.health.clone()))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    pub(crate) fn health(&self) -> Vec<UpstreamHealth> {
+        let mut health = self
+            .upstreams
+            .iter()
+            .map(|(name, upstream)| UpstreamHealth {
+                name: name.clone(),
+                endpoints: upstream
+                    .balancer
+                    .members()
+                    .iter()
+                    .map(|member| EndpointHealth {
+                        endpoint: member.endpoint.to_string(),
+                        status: match (&upstream.health_check, member.health.is_healthy()) {
+                            (None, _) => Status::Unchecked,
+                            (Some(_), true) => Status::Healthy,
+                            (Some(_), false) => Status::Unhealthy,
+                        },
+                        outstanding: member.outstanding(),
+                        last_error: member.health.last_error(),
+                    })
+                    .collect(),
+            })
+            .collect::<Vec<_>>();
+        health.sort_by(|a, b| a.name.cmp(&b.name));
+        health
+    }
+}
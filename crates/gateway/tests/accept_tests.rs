@@ -0,0 +1,55 @@
+//! Runs on its own, the limit of open files it lowers applies to the whole
+//! process.
+mod common;
+
+use anyhow::{Result, bail};
+use std::time::Duration;
+use tokio::net::TcpSocket;
+
+use common::{Stub, exchange, handshake, serve, tls};
+
+#[tokio::test(flavor = "multi_thread")]
+async fn failing_accepts_are_retried() -> Result<()> {
+    let stub = Stub::respond(200).await?;
+    let (_gateway, port) = serve(&tls(&stub.address(), 10_000)).await?;
+    let socket = TcpSocket::new_v4()?;
+
+    // Without a file descriptor to spare, accepting the connection fails
+    // until the limit is raised again.
+    let limit = open_files_limit()?;
+    set_open_files_limit(libc::rlimit {
+        rlim_cur: 0,
+        ..limit
+    })?;
+    let stream = socket.connect(([127, 0, 0, 1], port).into()).await;
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    set_open_files_limit(limit)?;
+    let stream = stream?;
+
+    let stream = tokio::time::timeout(Duration::from_secs(5), handshake(stream, &[])).await??;
+    let (status, _) = exchange(stream, "GET", "/", &[]).await?;
+
+    assert_eq!(status, 200);
+
+    Ok(())
+}
+
+fn open_files_limit() -> Result<libc::rlimit> {
+    let mut limit = libc::rlimit {
+        rlim_cur: 0,
+        rlim_max: 0,
+    };
+    // Safety: `limit` is a valid rlimit to write to.
+    if unsafe { libc::getrlimit(libc::RLIMIT_NOFILE, &mut limit) } != 0 {
+        bail!("Failed to read limit of open files");
+    }
+    Ok(limit)
+}
+
+fn set_open_files_limit(limit: libc::rlimit) -> Result<()> {
+    // Safety: `limit` is a valid rlimit to read from.
+    if unsafe { libc::setrlimit(libc::RLIMIT_NOFILE, &limit) } != 0 {
+        bail!("Failed to set limit of open files");
+    }
+    Ok(())
+}
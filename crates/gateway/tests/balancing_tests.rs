@@ -0,0 +1,180 @@
+mod common;
+
+use anyhow::Result;
+use garde::Validate;
+
+use common::{Stub, send, send_to, serve};
+use gateway::balancer::Ring;
+use gateway::configuration::Configuration;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn round_robin_follows_weights() -> Result<()> {
+    let light = Stub::respond(200).await?;
+    let heavy = Stub::respond(200).await?;
+    let (_gateway, port) = serve(&format!(
+        r#"
+        upstreams:
+          - name: backend
+            endpoints:
+              - {}
+              - address: {}
+                weight: 3
+        "#,
+        light.address(),
+        heavy.address()
+    ))
+    .await?;
+
+    for _ in 0..8 {
+        send(port, "GET").await?;
+    }
+
+    assert_eq!((light.requests(), heavy.requests()), (2, 6));
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn least_outstanding_spreads_idle_upstream_evenly() -> Result<()> {
+    let stubs = [
+        Stub::respond(200).await?,
+        Stub::respond(200).await?,
+        Stub::respond(200).await?,
+    ];
+    let (_gateway, port) = serve(&format!(
+        r#"
+        upstreams:
+          - name: backend
+            endpoints: [{}, {}, {}]
+            balancing: least_outstanding
+        "#,
+        stubs[0].address(),
+        stubs[1].address(),
+        stubs[2].address()
+    ))
+    .await?;
+
+    for _ in 0..6 {
+        send(port, "GET").await?;
+    }
+
+    assert_eq!(stubs.each_ref().map(Stub::requests), [2, 2, 2]);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn consistent_hash_keeps_key_on_one_endpoint() -> Result<()> {
+    let stubs = [
+        Stub::respond(200).await?,
+        Stub::respond(200).await?,
+        Stub::respond(200).await?,
+    ];
+    let (_gateway, port) = serve(&format!(
+        r#"
+        upstreams:
+          - name: backend
+            endpoints: [{}, {}, {}]
+            balancing:
+              consistent_hash:
+                header: x-user
+        "#,
+        stubs[0].address(),
+        stubs[1].address(),
+        stubs[2].address()
+    ))
+    .await?;
+
+    for _ in 0..6 {
+        send_to(port, "GET", "/", &[("x-user", "alice")]).await?;
+    }
+
+    let mut requests = stubs.each_ref().map(Stub::requests);
+    requests.sort();
+    assert_eq!(requests, [0, 0, 6]);
+
+    Ok(())
+}
+
+#[test]
+fn endpoints_need_http_scheme_and_bounded_weight() -> Result<()> {
+    let valid = |endpoint: &str| -> Result<bool> {
+        let configuration: Configuration = serde_yaml::from_str(&format!(
+            r#"
+            port: 8080
+            upstreams:
+              - name: backend
+                endpoints: [{}]
+            "#,
+            endpoint
+        ))?;
+        Ok(configuration.validate().is_ok())
+    };
+
+    assert!(valid("10.0.0.1:8080")?);
+    assert!(valid("http://backend.internal")?);
+    assert!(valid("{address: 10.0.0.1:8080, weight: 1000}")?);
+    assert!(valid("https://backend.internal")?);
+    assert!(!valid("ftp://backend.internal")?);
+    assert!(!valid("{address: 10.0.0.1:8080, weight: 1001}")?);
+
+    Ok(())
+}
+
+#[test]
+fn hash_past_the_last_point_wraps_around() {
+    let ring = ring();
+
+    assert_eq!(ring.owner(u64::MAX, |_| true), ring.owner(0, |_| true));
+}
+
+#[test]
+fn ring_is_the_same_for_the_same_members() {
+    let ring = ring();
+
+    let owners = (0..8)
+        .map(|i| ring.owner(i << 61, |_| true))
+        .collect::<Vec<_>>();
+    let expected = [0, 0, 1, 0, 0, 0, 2, 0].map(Some);
+    assert_eq!(owners, expected);
+}
+
+#[test]
+fn ring_points_follow_weights() {
+    let ring = ring();
+
+    let heavy = samples()
+        .filter(|hash| ring.owner(*hash, |_| true) == Some(2))
+        .count();
+    let share = heavy as f64 / samples().count() as f64;
+    assert!((0.4..0.6).contains(&share), "{}", share);
+}
+
+#[test]
+fn keys_of_unhealthy_member_move_to_the_next_one() {
+    let ring = ring();
+
+    for hash in samples() {
+        let owner = ring.owner(hash, |_| true);
+        let without_first = ring.owner(hash, |index| index != 0);
+        match owner {
+            Some(0) => assert_ne!(without_first, Some(0)),
+            owner => assert_eq!(without_first, owner),
+        }
+    }
+    assert_eq!(ring.owner(0, |_| false), None);
+}
+
+/// Ring of three endpoints, the last with twice the weight.
+fn ring() -> Ring {
+    Ring::new(&[
+        ("http://10.0.0.1:80/".to_string(), 1),
+        ("http://10.0.0.2:80/".to_string(), 1),
+        ("http://10.0.0.3:80/".to_string(), 2),
+    ])
+}
+
+/// Hashes spread evenly over the ring.
+fn samples() -> impl Iterator<Item = u64> {
+    (0..10_000u64).map(|i| i.wrapping_mul(0x9E37_79B9_7F4A_7C15))
+}
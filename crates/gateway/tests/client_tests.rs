@@ -0,0 +1,83 @@
+mod common;
+
+use anyhow::Result;
+use rama::http::dep::http_body_util::BodyExt;
+use rama::http::{Body, Request};
+use std::time::{Duration, Instant};
+
+use common::Stub;
+use gateway::client::{Error, UpstreamClient};
+use gateway::configuration::{client, timeouts};
+
+#[tokio::test(flavor = "multi_thread")]
+async fn connections_are_reused() -> Result<()> {
+    let stub = Stub::keep_alive().await?;
+    let client = UpstreamClient::new(&Default::default())?;
+
+    for _ in 0..3 {
+        let response = client.send(request(&stub)?, &Default::default()).await?;
+        response.into_body().collect().await?;
+    }
+
+    assert_eq!(stub.requests(), 3);
+    assert_eq!(stub.connections(), 1);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn requests_wait_for_a_free_slot_of_their_host() -> Result<()> {
+    let stub = Stub::keep_alive().await?;
+    let client = UpstreamClient::new(&client::Configuration {
+        max_requests_per_host: 1,
+        ..Default::default()
+    })?;
+
+    let held = client.send(request(&stub)?, &Default::default()).await?;
+    let waiting = tokio::spawn({
+        let client = client.clone();
+        let request = request(&stub)?;
+        async move { client.send(request, &Default::default()).await }
+    });
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    assert!(!waiting.is_finished());
+    assert_eq!(stub.requests(), 1);
+
+    drop(held);
+    let response = tokio::time::timeout(Duration::from_secs(5), waiting).await???;
+    response.into_body().collect().await?;
+    assert_eq!(stub.requests(), 2);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn waiting_for_a_slot_ends_after_the_pool_timeout() -> Result<()> {
+    let stub = Stub::keep_alive().await?;
+    let client = UpstreamClient::new(&client::Configuration {
+        max_requests_per_host: 1,
+        pool_timeout_ms: 100,
+        ..Default::default()
+    })?;
+    let timeouts = timeouts::Configuration {
+        read_timeout_ms: 10_000,
+        ..Default::default()
+    };
+
+    let _held = client.send(request(&stub)?, &timeouts).await?;
+    let started = Instant::now();
+    let error = client.send(request(&stub)?, &timeouts).await.unwrap_err();
+
+    assert!(matches!(error, Error::Timeout), "{}", error);
+    assert!(started.elapsed() < Duration::from_secs(5));
+    assert_eq!(stub.requests(), 1);
+
+    Ok(())
+}
+
+fn request(stub: &Stub) -> Result<Request> {
+    let request = Request::builder()
+        .uri(format!("http://{}/", stub.address()))
+        .body(Body::empty())?;
+    Ok(request)
+}
@@ -0,0 +1,394 @@
+//! Helpers shared by the gateway tests, upstreams are played by a minimal
+//! HTTP/1.1 stub.
+#![allow(dead_code)]
+
+use anyhow::{Context as _, Result};
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::crypto::aws_lc_rs::default_provider;
+use rustls::crypto::{WebPkiSupportedAlgorithms, verify_tls12_signature, verify_tls13_signature};
+use rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+use rustls::{ClientConfig, DigitallySignedStruct, SignatureScheme};
+use std::sync::atomic::{AtomicU16, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio_rustls::TlsConnector;
+use tokio_rustls::client::TlsStream;
+
+use gateway::Gateway;
+use gateway::configuration::Configuration;
+use runtime::Runtime;
+use runtime::kv::KeyValueStore;
+
+pub fn gateway(configuration: &str) -> Result<Gateway> {
+    let configuration: Configuration = serde_yaml::from_str(configuration)?;
+    Gateway::new(&configuration)
+}
+
+/// Serves the gateway on a free local port with a component forwarding every
+/// request to the upstream `backend`, returning once it accepts connections.
+pub async fn serve(configuration: &str) -> Result<(Arc<Gateway>, u16)> {
+    serve_component(configuration, "forward_to").await
+}
+
+/// Serves the gateway like `serve`, with the test component `name` of the
+/// runtime crate.
+pub async fn serve_component(configuration: &str, name: &str) -> Result<(Arc<Gateway>, u16)> {
+    let mut configuration: Configuration = serde_yaml::from_str(configuration)?;
+    configuration.port = std::net::TcpListener::bind("127.0.0.1:0")?
+        .local_addr()?
+        .port();
+    let port = configuration.port;
+    let gateway = Arc::new(Gateway::new(&configuration)?);
+    let component = wat::parse_file(format!(
+        "{}/../runtime/tests/components/{}.wat",
+        env!("CARGO_MANIFEST_DIR"),
+        name
+    ))?;
+    let runtime = Runtime::new(&Default::default(), Arc::new(EmptyStore), &component)?;
+    let serving = gateway.clone();
+    tokio::spawn(async move { serving.run(runtime).await });
+    for _ in 0..250 {
+        if TcpStream::connect(("127.0.0.1", port)).await.is_ok() {
+            return Ok((gateway, port));
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    anyhow::bail!("Gateway did not start listening on port {}", port)
+}
+
+/// Sends a request without body through the gateway, returning the status
+/// and whatever arrived of the body before the connection closed.
+pub async fn send(port: u16, method: &str) -> Result<(u16, Vec<u8>)> {
+    send_to(port, method, "/", &[]).await
+}
+
+pub async fn send_to(
+    port: u16,
+    method: &str,
+    path: &str,
+    headers: &[(&str, &str)],
+) -> Result<(u16, Vec<u8>)> {
+    let stream = TcpStream::connect(("127.0.0.1", port)).await?;
+    exchange(stream, method, path, headers).await
+}
+
+/// Sends a request with `body` through the gateway.
+pub async fn send_body(port: u16, method: &str, body: &str) -> Result<(u16, Vec<u8>)> {
+    let stream = TcpStream::connect(("127.0.0.1", port)).await?;
+    exchange_body(stream, method, "/", &[], body).await
+}
+
+/// Sends a request without body on `stream`, the connection is closed after
+/// the response.
+pub async fn exchange(
+    stream: impl AsyncRead + AsyncWrite + Unpin,
+    method: &str,
+    path: &str,
+    headers: &[(&str, &str)],
+) -> Result<(u16, Vec<u8>)> {
+    exchange_body(stream, method, path, headers, "").await
+}
+
+async fn exchange_body(
+    mut stream: impl AsyncRead + AsyncWrite + Unpin,
+    method: &str,
+    path: &str,
+    headers: &[(&str, &str)],
+    body: &str,
+) -> Result<(u16, Vec<u8>)> {
+    let mut head = format!(
+        "{} {} HTTP/1.1\r\nhost: gateway\r\ncontent-length: {}\r\nconnection: close\r\n",
+        method,
+        path,
+        body.len()
+    );
+    for (name, value) in headers {
+        head.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    head.push_str("\r\n");
+    head.push_str(body);
+    stream.write_all(head.as_bytes()).await?;
+    let mut response = Vec::new();
+    // A cut off body may end in a reset, what was read until then is kept.
+    let _ = stream.read_to_end(&mut response).await;
+    let response = String::from_utf8_lossy(&response);
+    let (head, body) = response
+        .split_once("\r\n\r\n")
+        .context("Incomplete response head")?;
+    let status = head.split(' ').nth(1).context("Missing status")?.parse()?;
+    Ok((status, body.as_bytes().to_vec()))
+}
+
+/// Gateway terminating TLS with the default fixture and forwarding to the
+/// upstream `backend` at `address`.
+pub fn tls(address: &str, handshake_timeout_ms: u64) -> String {
+    let fixture = |file: &str| {
+        std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures")
+            .join(file)
+    };
+    format!(
+        r#"
+        tls:
+          handshake_timeout_ms: {}
+          certificates:
+            - certificate: {:?}
+              key: {:?}
+        upstreams:
+          - name: backend
+            endpoints: [{}]
+        "#,
+        handshake_timeout_ms,
+        fixture("default.pem"),
+        fixture("default.key"),
+        address
+    )
+}
+
+/// Connects to the gateway over TLS offering `protocols` through ALPN.
+pub async fn connect_tls(port: u16, protocols: &[&[u8]]) -> Result<TlsStream<TcpStream>> {
+    let stream = TcpStream::connect(("127.0.0.1", port)).await?;
+    handshake(stream, protocols).await
+}
+
+/// Completes the TLS handshake on `stream`, the certificate of the gateway is
+/// accepted without being verified.
+pub async fn handshake(stream: TcpStream, protocols: &[&[u8]]) -> Result<TlsStream<TcpStream>> {
+    let mut config = ClientConfig::builder_with_provider(default_provider().into())
+        .with_safe_default_protocol_versions()?
+        .dangerous()
+        .with_custom_certificate_verifier(Arc::new(AnyCertificate(
+            default_provider().signature_verification_algorithms,
+        )))
+        .with_no_client_auth();
+    config.alpn_protocols = protocols.iter().map(|protocol| protocol.to_vec()).collect();
+    let server_name = ServerName::try_from("gateway")?;
+    Ok(TlsConnector::from(Arc::new(config))
+        .connect(server_name, stream)
+        .await?)
+}
+
+/// Trusts any certificate, as long as the handshake is signed with its key.
+#[derive(Debug)]
+struct AnyCertificate(WebPkiSupportedAlgorithms);
+
+impl ServerCertVerifier for AnyCertificate {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        certificate: &CertificateDer<'_>,
+        signature: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls12_signature(message, certificate, signature, &self.0)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        certificate: &CertificateDer<'_>,
+        signature: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls13_signature(message, certificate, signature, &self.0)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.0.supported_schemes()
+    }
+}
+
+/// How the stub answers requests.
+#[derive(Debug, Clone, Copy)]
+enum Reply {
+    /// The current status with an empty body.
+    Status,
+    /// The current status, after waiting.
+    Delayed(Duration),
+    /// Headers announcing a body of which only the first bytes arrive, the
+    /// connection is then held open.
+    Truncated,
+    /// The current status with an empty body, the connection is kept open
+    /// for further requests.
+    KeepAlive,
+}
+
+/// Minimal HTTP/1.1 server answering every request on its own connection,
+/// unless kept alive, remembering what it was asked for.
+pub struct Stub {
+    port: u16,
+    status: Arc<AtomicU16>,
+    connections: Arc<AtomicUsize>,
+    requests: Arc<AtomicUsize>,
+    hang_ups: Arc<AtomicUsize>,
+    last_method: Arc<Mutex<String>>,
+    last_path: Arc<Mutex<String>>,
+}
+
+impl Stub {
+    pub async fn respond(status: u16) -> Result<Self> {
+        Self::start(status, Reply::Status).await
+    }
+
+    pub async fn delayed(delay: Duration) -> Result<Self> {
+        Self::start(200, Reply::Delayed(delay)).await
+    }
+
+    pub async fn truncated() -> Result<Self> {
+        Self::start(200, Reply::Truncated).await
+    }
+
+    pub async fn keep_alive() -> Result<Self> {
+        Self::start(200, Reply::KeepAlive).await
+    }
+
+    async fn start(status: u16, reply: Reply) -> Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let stub = Self {
+            port: listener.local_addr()?.port(),
+            status: Arc::new(AtomicU16::new(status)),
+            connections: Default::default(),
+            requests: Default::default(),
+            hang_ups: Default::default(),
+            last_method: Default::default(),
+            last_path: Default::default(),
+        };
+        let (status, connections, requests) = (
+            stub.status.clone(),
+            stub.connections.clone(),
+            stub.requests.clone(),
+        );
+        let (hang_ups, last_method, last_path) = (
+            stub.hang_ups.clone(),
+            stub.last_method.clone(),
+            stub.last_path.clone(),
+        );
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                connections.fetch_add(1, Ordering::Relaxed);
+                let (status, requests) = (status.clone(), requests.clone());
+                let (hang_ups, last_method, last_path) =
+                    (hang_ups.clone(), last_method.clone(), last_path.clone());
+                tokio::spawn(async move {
+                    let mut buffer = [0; 4096];
+                    loop {
+                        let read = match stream.read(&mut buffer).await {
+                            Ok(read) if read > 0 => read,
+                            _ => return,
+                        };
+                        let head = String::from_utf8_lossy(&buffer[..read]);
+                        let mut request_line = head.split(' ');
+                        if let Some(method) = request_line.next() {
+                            *last_method.lock().unwrap() = method.to_string();
+                        }
+                        if let Some(path) = request_line.next() {
+                            *last_path.lock().unwrap() = path.to_string();
+                        }
+                        requests.fetch_add(1, Ordering::Relaxed);
+                        let hang_up = hang_ups
+                            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |left| {
+                                left.checked_sub(1)
+                            })
+                            .is_ok();
+                        if hang_up {
+                            return;
+                        }
+                        let status = status.load(Ordering::Relaxed);
+                        let response = match reply {
+                            Reply::Status => empty(status),
+                            Reply::Delayed(delay) => {
+                                tokio::time::sleep(delay).await;
+                                empty(status)
+                            }
+                            Reply::Truncated => format!(
+                                "HTTP/1.1 {} Stub\r\ncontent-length: 10\r\n\r\nhello",
+                                status
+                            ),
+                            Reply::KeepAlive => {
+                                format!("HTTP/1.1 {} Stub\r\ncontent-length: 0\r\n\r\n", status)
+                            }
+                        };
+                        let _ = stream.write_all(response.as_bytes()).await;
+                        match reply {
+                            Reply::Truncated => tokio::time::sleep(Duration::from_secs(10)).await,
+                            Reply::KeepAlive => continue,
+                            _ => {}
+                        }
+                        return;
+                    }
+                });
+            }
+        });
+        Ok(stub)
+    }
+
+    pub fn address(&self) -> String {
+        format!("127.0.0.1:{}", self.port)
+    }
+
+    pub fn set_status(&self, status: u16) {
+        self.status.store(status, Ordering::Relaxed);
+    }
+
+    /// Closes the connection of the next `requests` requests without
+    /// answering them.
+    pub fn hang_up(&self, requests: usize) {
+        self.hang_ups.store(requests, Ordering::Relaxed);
+    }
+
+    pub fn connections(&self) -> usize {
+        self.connections.load(Ordering::Relaxed)
+    }
+
+    pub fn requests(&self) -> usize {
+        self.requests.load(Ordering::Relaxed)
+    }
+
+    pub fn last_method(&self) -> String {
+        self.last_method.lock().unwrap().clone()
+    }
+
+    pub fn last_path(&self) -> String {
+        self.last_path.lock().unwrap().clone()
+    }
+}
+
+fn empty(status: u16) -> String {
+    format!(
+        "HTTP/1.1 {} Stub\r\ncontent-length: 0\r\nconnection: close\r\n\r\n",
+        status
+    )
+}
+
+/// Store for components that never touch the `kv` interface.
+struct EmptyStore;
+
+#[async_trait::async_trait]
+impl KeyValueStore for EmptyStore {
+    async fn get(&self, _namespace: &str, _key: &str) -> Result<Option<Vec<u8>>> {
+        Ok(None)
+    }
+
+    async fn set(&self, _namespace: &str, _key: &str, _value: Vec<u8>) -> Result<()> {
+        Ok(())
+    }
+
+    async fn delete(&self, _namespace: &str, _key: &str) -> Result<()> {
+        Ok(())
+    }
+
+    async fn increment(&self, _namespace: &str, _key: &str, delta: i64) -> Result<i64> {
+        Ok(delta)
+    }
+}
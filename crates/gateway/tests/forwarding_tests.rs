@@ -0,0 +1,166 @@
+mod common;
+
+use anyhow::Result;
+use std::time::{Duration, Instant};
+use tokio::net::TcpListener;
+
+use common::{Stub, send, send_body, serve, serve_component};
+
+#[tokio::test(flavor = "multi_thread")]
+async fn idempotent_request_is_retried() -> Result<()> {
+    let stub = Stub::respond(200).await?;
+    stub.hang_up(1);
+    let (_gateway, port) = serve(&backend(&stub.address(), "")).await?;
+
+    let (status, _) = send(port, "GET").await?;
+
+    assert_eq!(status, 200);
+    assert_eq!(stub.requests(), 2);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn non_idempotent_request_is_not_retried() -> Result<()> {
+    let stub = Stub::respond(200).await?;
+    stub.hang_up(1);
+    let (_gateway, port) = serve(&backend(&stub.address(), "")).await?;
+
+    let (status, _) = send(port, "POST").await?;
+
+    assert_eq!(status, 502);
+    assert_eq!(stub.requests(), 1);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn request_with_body_is_not_retried() -> Result<()> {
+    let stub = Stub::respond(200).await?;
+    let (_gateway, port) = serve(&backend(&stub.address(), "")).await?;
+
+    for method in ["POST", "PUT"] {
+        stub.hang_up(1);
+        let requests = stub.requests();
+
+        let (status, _) = send_body(port, method, "hello").await?;
+
+        assert_eq!(status, 502, "{}", method);
+        assert_eq!(stub.requests(), requests + 1, "{}", method);
+    }
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn error_status_is_not_retried() -> Result<()> {
+    let stub = Stub::respond(503).await?;
+    let (_gateway, port) = serve(&backend(&stub.address(), "")).await?;
+
+    let (status, _) = send(port, "GET").await?;
+
+    assert_eq!(status, 503);
+    assert_eq!(stub.requests(), 1);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn exhausted_budget_stops_retries() -> Result<()> {
+    let stub = Stub::respond(200).await?;
+    stub.hang_up(1);
+    let retry = r#"
+            retry:
+              budget_ratio: 0.0
+              min_retries_per_second: 0
+    "#;
+    let (_gateway, port) = serve(&backend(&stub.address(), retry)).await?;
+
+    let (status, _) = send(port, "GET").await?;
+
+    assert_eq!(status, 502);
+    assert_eq!(stub.requests(), 1);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn slow_upstream_fails_with_gateway_timeout() -> Result<()> {
+    let stub = Stub::delayed(Duration::from_secs(5)).await?;
+    let timeouts = r#"
+            timeouts:
+              read_timeout_ms: 100
+            retry:
+              max_attempts: 1
+    "#;
+    let (_gateway, port) = serve(&backend(&stub.address(), timeouts)).await?;
+
+    let (status, _) = send(port, "GET").await?;
+
+    assert_eq!(status, 504);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn unreachable_upstream_fails_with_bad_gateway() -> Result<()> {
+    let closed = TcpListener::bind("127.0.0.1:0").await?.local_addr()?;
+    let retry = r#"
+            retry:
+              max_attempts: 1
+    "#;
+    let (_gateway, port) = serve(&backend(&closed.to_string(), retry)).await?;
+
+    let (status, _) = send(port, "GET").await?;
+
+    assert_eq!(status, 502);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn total_timeout_cuts_off_response_body() -> Result<()> {
+    let stub = Stub::truncated().await?;
+    let timeouts = r#"
+            timeouts:
+              total_timeout_ms: 300
+    "#;
+    let (_gateway, port) = serve(&backend(&stub.address(), timeouts)).await?;
+    let started = Instant::now();
+
+    let (status, body) = send(port, "GET").await?;
+
+    assert_eq!(status, 200);
+    assert!(body.len() < 10, "{:?}", body);
+    assert!(started.elapsed() < Duration::from_secs(5));
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn method_set_by_component_reaches_upstream() -> Result<()> {
+    let stub = Stub::respond(200).await?;
+    let (_gateway, port) = serve_component(&backend(&stub.address(), ""), "method").await?;
+
+    let (status, _) = send(port, "GET").await?;
+
+    assert_eq!(status, 200);
+    assert_eq!(stub.last_method(), "PURGE");
+
+    Ok(())
+}
+
+/// Upstream `backend` with the single endpoint `address`, `settings` are
+/// added to it.
+fn backend(address: &str, settings: &str) -> String {
+    format!(
+        r#"
+        upstreams:
+          - name: backend
+            endpoints: [{}]
+            {}
+        "#,
+        address,
+        settings.trim()
+    )
+}
@@ -0,0 +1,242 @@
+mod common;
+
+use anyhow::Result;
+use std::time::Duration;
+use tokio::net::TcpListener;
+
+use common::{Stub, gateway, send, send_to, serve};
+use gateway::Gateway;
+use gateway::health::{Status, UpstreamHealth};
+
+#[tokio::test(flavor = "multi_thread")]
+async fn failing_endpoint_is_ejected() -> Result<()> {
+    let healthy = Stub::respond(200).await?;
+    let failing = Stub::respond(503).await?;
+    let gateway = gateway(&format!(
+        r#"
+        upstreams:
+          - name: backend
+            endpoints: [{}, {}]
+            health_check:
+              interval_ms: 20
+              unhealthy_threshold: 2
+        "#,
+        healthy.address(),
+        failing.address()
+    ))?;
+    gateway.check_health()?;
+
+    let health = eventually(&gateway, |health| {
+        health[0].endpoints[1].status == Status::Unhealthy
+    })
+    .await;
+    assert_eq!(health[0].endpoints[0].status, Status::Healthy);
+    let last_error = health[0].endpoints[1]
+        .last_error
+        .clone()
+        .unwrap_or_default();
+    assert!(last_error.contains("503"), "{}", last_error);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn expected_status_and_path_are_configurable() -> Result<()> {
+    let stub = Stub::respond(204).await?;
+    let gateway = gateway(&format!(
+        r#"
+        upstreams:
+          - name: backend
+            endpoints: [{}]
+            health_check:
+              probe:
+                http:
+                  path: /healthz
+                  expected_status: [204]
+              interval_ms: 20
+              unhealthy_threshold: 1
+        "#,
+        stub.address()
+    ))?;
+    gateway.check_health()?;
+
+    eventually(&gateway, |_| stub.requests() >= 3).await;
+    let health = gateway.upstream_health();
+    assert_eq!(health[0].endpoints[0].status, Status::Healthy);
+    assert_eq!(health[0].endpoints[0].last_error, None);
+    assert_eq!(stub.last_path(), "/healthz");
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn endpoint_recovers_after_healthy_threshold() -> Result<()> {
+    let stub = Stub::respond(500).await?;
+    let gateway = gateway(&format!(
+        r#"
+        upstreams:
+          - name: backend
+            endpoints: [{}]
+            health_check:
+              interval_ms: 20
+              healthy_threshold: 2
+              unhealthy_threshold: 1
+        "#,
+        stub.address()
+    ))?;
+    gateway.check_health()?;
+
+    eventually(&gateway, |health| {
+        health[0].endpoints[0].status == Status::Unhealthy
+    })
+    .await;
+    stub.set_status(200);
+    let health = eventually(&gateway, |health| {
+        health[0].endpoints[0].status == Status::Healthy
+    })
+    .await;
+    assert_eq!(health[0].endpoints[0].last_error, None);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn tcp_probe_detects_closed_port() -> Result<()> {
+    let open = Stub::respond(200).await?;
+    let closed = TcpListener::bind("127.0.0.1:0").await?.local_addr()?;
+    let gateway = gateway(&format!(
+        r#"
+        upstreams:
+          - name: backend
+            endpoints: [{}, {}]
+            health_check:
+              probe: tcp
+              interval_ms: 20
+              unhealthy_threshold: 1
+        "#,
+        open.address(),
+        closed
+    ))?;
+    gateway.check_health()?;
+
+    let health = eventually(&gateway, |health| {
+        health[0].endpoints[1].status == Status::Unhealthy
+    })
+    .await;
+    assert_eq!(health[0].endpoints[0].status, Status::Healthy);
+    assert_eq!(open.requests(), 0);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn upstream_without_health_check_is_unchecked() -> Result<()> {
+    let gateway = gateway(
+        r#"
+        upstreams:
+          - name: backend
+            endpoints: [127.0.0.1:1]
+        "#,
+    )?;
+    gateway.check_health()?;
+
+    let health = gateway.upstream_health();
+    assert_eq!(health.len(), 1);
+    assert_eq!(health[0].name, "backend");
+    assert_eq!(health[0].endpoints[0].status, Status::Unchecked);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn health_is_checked_once() -> Result<()> {
+    let stub = Stub::respond(200).await?;
+    let gateway = gateway(&format!(
+        r#"
+        upstreams:
+          - name: backend
+            endpoints: [{}]
+            health_check:
+              interval_ms: 60000
+        "#,
+        stub.address()
+    ))?;
+
+    gateway.check_health()?;
+    gateway.check_health()?;
+    tokio::time::sleep(Duration::from_millis(200)).await;
+
+    assert_eq!(stub.requests(), 1);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn unhealthy_endpoint_receives_no_requests() -> Result<()> {
+    let healthy = Stub::respond(200).await?;
+    let failing = Stub::respond(503).await?;
+    let (gateway, port) = serve(&format!(
+        r#"
+        upstreams:
+          - name: backend
+            endpoints: [{}, {}]
+            health_check:
+              interval_ms: 20
+              unhealthy_threshold: 1
+        "#,
+        healthy.address(),
+        failing.address()
+    ))
+    .await?;
+
+    eventually(&gateway, |health| {
+        health[0].endpoints[1].status == Status::Unhealthy
+    })
+    .await;
+
+    for _ in 0..4 {
+        assert_eq!(send(port, "GET").await?.0, 200);
+    }
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn health_is_served_on_admin_routes() -> Result<()> {
+    let gateway = gateway(
+        r#"
+        upstreams:
+          - name: backend
+            endpoints: [127.0.0.1:1]
+        "#,
+    )?;
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let port = listener.local_addr()?.port();
+    let routes = gateway.admin_routes();
+    tokio::spawn(async move { axum::serve(listener, routes).await });
+
+    let (status, body) = send_to(port, "GET", "/upstreams/health", &[]).await?;
+
+    assert_eq!(status, 200);
+    let health: serde_json::Value = serde_json::from_slice(&body)?;
+    assert_eq!(health[0]["name"], "backend");
+    assert_eq!(health[0]["endpoints"][0]["status"], "unchecked");
+
+    Ok(())
+}
+
+/// Waits until the reported health satisfies `done`, failing after a few
+/// seconds.
+async fn eventually(
+    gateway: &Gateway,
+    done: impl Fn(&[UpstreamHealth]) -> bool,
+) -> Vec<UpstreamHealth> {
+    for _ in 0..250 {
+        let health = gateway.upstream_health();
+        if done(&health) {
+            return health;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    panic!("Health never reached the expected state");
+}
@@ -0,0 +1,53 @@
+use std::time::Duration;
+
+use gateway::configuration::retry;
+use gateway::retry::Policy;
+
+#[test]
+fn backoff_doubles_up_to_the_maximum() {
+    let policy = policy(retry::Configuration {
+        initial_backoff_ms: 10,
+        max_backoff_ms: 100,
+        ..Default::default()
+    });
+
+    for retry in 1..=40 {
+        let ceiling = Duration::from_millis((10 << (retry - 1).min(16)).min(100));
+        for _ in 0..50 {
+            let backoff = policy.backoff(retry);
+            assert!(backoff <= ceiling, "{:?} above {:?}", backoff, ceiling);
+        }
+    }
+}
+
+#[test]
+fn retries_are_limited_to_the_budget_ratio() {
+    let policy = policy(retry::Configuration {
+        budget_ratio: 0.5,
+        min_retries_per_second: 0,
+        ..Default::default()
+    });
+    for _ in 0..4 {
+        policy.record_request();
+    }
+
+    assert!(policy.try_retry());
+    assert!(policy.try_retry());
+    assert!(!policy.try_retry());
+}
+
+#[test]
+fn minimum_retries_are_allowed_without_traffic() {
+    let policy = policy(retry::Configuration {
+        budget_ratio: 0.0,
+        min_retries_per_second: 1,
+        ..Default::default()
+    });
+
+    let allowed = (0..20).take_while(|_| policy.try_retry()).count();
+    assert_eq!(allowed, 10);
+}
+
+fn policy(retry: retry::Configuration) -> Policy {
+    Policy::new(Default::default(), retry)
+}
@@ -0,0 +1,219 @@
+mod common;
+
+use anyhow::Result;
+use rustls::pki_types::CertificateDer;
+use rustls::pki_types::pem::PemObject;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use tokio::io::AsyncReadExt;
+use tokio::net::TcpStream;
+
+use common::{Stub, connect_tls, exchange, serve, tls};
+use gateway::configuration::tls::{Certificate, Configuration};
+use gateway::tls::CertificateStore;
+
+#[test]
+fn exact_server_name_gets_its_certificate() -> Result<()> {
+    let store = CertificateStore::new(&fixtures())?;
+
+    assert_eq!(served(&store, Some("api.example.com")), fixture("exact")?);
+    assert_eq!(served(&store, Some("API.Example.com")), fixture("exact")?);
+
+    Ok(())
+}
+
+#[test]
+fn wildcard_matches_a_single_label() -> Result<()> {
+    let store = CertificateStore::new(&fixtures())?;
+
+    assert_eq!(
+        served(&store, Some("www.example.com")),
+        fixture("wildcard")?
+    );
+    assert_eq!(
+        served(&store, Some("a.www.example.com")),
+        fixture("default")?
+    );
+
+    Ok(())
+}
+
+#[test]
+fn other_clients_get_the_default_certificate() -> Result<()> {
+    let store = CertificateStore::new(&fixtures())?;
+
+    assert_eq!(served(&store, Some("example.org")), fixture("default")?);
+    assert_eq!(served(&store, None), fixture("default")?);
+
+    Ok(())
+}
+
+#[test]
+fn changed_files_are_reloaded() -> Result<()> {
+    let directory = tempfile::tempdir()?;
+    let started = SystemTime::now();
+    install(directory.path(), "exact", started)?;
+    let store = CertificateStore::new(&installed(directory.path()))?;
+
+    install(
+        directory.path(),
+        "default",
+        started + Duration::from_secs(10),
+    )?;
+    store.reload_if_changed()?;
+
+    assert_eq!(served(&store, None), fixture("default")?);
+
+    Ok(())
+}
+
+#[test]
+fn failed_reload_is_retried() -> Result<()> {
+    let directory = tempfile::tempdir()?;
+    let started = SystemTime::now();
+    install(directory.path(), "exact", started)?;
+    let store = CertificateStore::new(&installed(directory.path()))?;
+
+    // The check runs while the files are being replaced, once they are
+    // complete their modification times do not change again.
+    let modified = started + Duration::from_secs(10);
+    fs::write(directory.path().join("server.pem"), "incomplete")?;
+    for file in ["server.pem", "server.key"] {
+        let file = File::options()
+            .write(true)
+            .open(directory.path().join(file))?;
+        file.set_modified(modified)?;
+    }
+    assert!(store.reload_if_changed().is_err());
+    assert_eq!(served(&store, None), fixture("exact")?);
+
+    install(directory.path(), "default", modified)?;
+    store.reload_if_changed()?;
+
+    assert_eq!(served(&store, None), fixture("default")?);
+
+    Ok(())
+}
+
+#[test]
+fn key_of_another_certificate_is_not_loaded() -> Result<()> {
+    let directory = tempfile::tempdir()?;
+    let started = SystemTime::now();
+    install(directory.path(), "exact", started)?;
+    let store = CertificateStore::new(&installed(directory.path()))?;
+
+    let target = directory.path().join("server.key");
+    fs::copy(fixtures_dir().join("default.key"), &target)?;
+    File::options()
+        .write(true)
+        .open(&target)?
+        .set_modified(started + Duration::from_secs(10))?;
+
+    assert!(store.reload_if_changed().is_err());
+    assert_eq!(served(&store, None), fixture("exact")?);
+    assert!(CertificateStore::new(&installed(directory.path())).is_err());
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn handshake_negotiates_h2() -> Result<()> {
+    let stub = Stub::respond(200).await?;
+    let (_gateway, port) = serve(&tls(&stub.address(), 10_000)).await?;
+
+    let stream = connect_tls(port, &[b"h2", b"http/1.1"]).await?;
+
+    assert_eq!(stream.get_ref().1.alpn_protocol(), Some(&b"h2"[..]));
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn request_is_served_over_tls() -> Result<()> {
+    let stub = Stub::respond(200).await?;
+    let (_gateway, port) = serve(&tls(&stub.address(), 10_000)).await?;
+
+    let stream = connect_tls(port, &[b"http/1.1"]).await?;
+    let (status, _) = exchange(stream, "GET", "/", &[]).await?;
+
+    assert_eq!(status, 200);
+    assert_eq!(stub.requests(), 1);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn stalled_handshake_is_dropped() -> Result<()> {
+    let stub = Stub::respond(200).await?;
+    let (_gateway, port) = serve(&tls(&stub.address(), 100)).await?;
+
+    let mut stream = TcpStream::connect(("127.0.0.1", port)).await?;
+    let read = tokio::time::timeout(Duration::from_secs(5), stream.read(&mut [0; 1])).await?;
+
+    assert!(read.is_err() || read.is_ok_and(|read| read == 0));
+
+    Ok(())
+}
+
+/// Exact, wildcard and default certificate of the fixtures.
+fn fixtures() -> Configuration {
+    let certificate = |name: &str, server_names: &[&str]| Certificate {
+        server_names: server_names.iter().map(|name| name.to_string()).collect(),
+        certificate: fixtures_dir().join(format!("{}.pem", name)),
+        key: fixtures_dir().join(format!("{}.key", name)),
+    };
+    Configuration {
+        certificates: vec![
+            certificate("exact", &["api.example.com"]),
+            certificate("wildcard", &["*.example.com"]),
+            certificate("default", &[]),
+        ],
+        reload_interval_ms: 5000,
+        handshake_timeout_ms: 10_000,
+    }
+}
+
+/// Default certificate at `server.pem` and `server.key` in `directory`.
+fn installed(directory: &Path) -> Configuration {
+    Configuration {
+        certificates: vec![Certificate {
+            server_names: Vec::new(),
+            certificate: directory.join("server.pem"),
+            key: directory.join("server.key"),
+        }],
+        reload_interval_ms: 5000,
+        handshake_timeout_ms: 10_000,
+    }
+}
+
+/// Copies the fixture `name` to `server.pem` and `server.key`, both modified
+/// at `modified`.
+fn install(directory: &Path, name: &str, modified: SystemTime) -> Result<()> {
+    for extension in ["pem", "key"] {
+        let target = directory.join(format!("server.{}", extension));
+        fs::copy(
+            fixtures_dir().join(format!("{}.{}", name, extension)),
+            &target,
+        )?;
+        File::options()
+            .write(true)
+            .open(&target)?
+            .set_modified(modified)?;
+    }
+    Ok(())
+}
+
+/// End-entity certificate served to clients asking for `server_name`.
+fn served(store: &CertificateStore, server_name: Option<&str>) -> Option<CertificateDer<'static>> {
+    store.certificate_for(server_name)?.cert.first().cloned()
+}
+
+fn fixture(name: &str) -> Result<Option<CertificateDer<'static>>> {
+    let path = fixtures_dir().join(format!("{}.pem", name));
+    Ok(Some(CertificateDer::from_pem_file(path)?))
+}
+
+fn fixtures_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures")
+}
@@ -10,6 +10,7 @@ impl Guest for Component {
     fn handle() -> Resolution {
         let response = Response {
             status_code: 404,
+            headers: Vec::new(),
             body: None,
         };
         Resolution::Respond(response)
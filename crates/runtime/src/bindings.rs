@@ -1,16 +1,33 @@
+pub(crate) mod v0_1;
+
 use wasmtime::component::{HasSelf, Linker, bindgen};
 
 use super::context::Context;
 
 bindgen!({
     path: "../../crates/proxy/wit",
-    world: "crossroads",
+    world: "crossroads-response-hook",
+    imports: { default: async },
+    exports: { default: async },
 });
 
+/// Links the host interfaces of every supported world version, the linker
+/// keeps `0.1.x` and `0.2.x` apart.
 pub(crate) fn add_to_linker(linker: &mut Linker<Context>) -> Result<(), anyhow::Error> {
+    v0_1::add_to_linker(linker)?;
     wit::crossroads::types::add_to_linker::<_, HasSelf<_>>(linker, |state| state)?;
-    wit::crossroads::request::add_to_linker::<_, HasSelf<_>>(linker, |state| state)
+    wit::crossroads::request::add_to_linker::<_, HasSelf<_>>(linker, |state| state)?;
+    wit::crossroads::upstream_response::add_to_linker::<_, HasSelf<_>>(linker, |state| state)?;
+    wit::crossroads::client::add_to_linker::<_, HasSelf<_>>(linker, |state| state)?;
+    wit::crossroads::kv::add_to_linker::<_, HasSelf<_>>(linker, |state| state)?;
+    wit::crossroads::config::add_to_linker::<_, HasSelf<_>>(linker, |state| state)?;
+    wit::crossroads::logging::add_to_linker::<_, HasSelf<_>>(linker, |state| state)
 }
 
-pub(crate) use wit::crossroads::request::Host as Request;
-pub(crate) use wit::crossroads::types::{Host, Resolution, Response};
+pub(crate) use wit::crossroads::client::{Error as ClientError, Host as Client, OutgoingRequest};
+pub(crate) use wit::crossroads::config::Host as Config;
+pub(crate) use wit::crossroads::kv::Host as KeyValue;
+pub(crate) use wit::crossroads::logging::{Host as Logging, Level};
+pub(crate) use wit::crossroads::request::{Host as Request, HttpMethod as Method};
+pub(crate) use wit::crossroads::types::{Host, Resolution, Response, Target};
+pub(crate) use wit::crossroads::upstream_response::Host as UpstreamResponse;
@@ -0,0 +1,36 @@
+//! Host bindings of the first `wit:crossroads@0.1.0` world, kept so
+//! components built against it keep working next to newer ones.
+
+use wasmtime::component::{HasSelf, Linker, bindgen};
+
+use crate::context::Context;
+
+bindgen!({
+    path: "wit/0.1.0",
+    world: "crossroads",
+    imports: { default: async },
+    exports: { default: async },
+});
+
+pub(crate) fn add_to_linker(linker: &mut Linker<Context>) -> Result<(), anyhow::Error> {
+    wit::crossroads::types::add_to_linker::<_, HasSelf<_>>(linker, |state| state)?;
+    wit::crossroads::request::add_to_linker::<_, HasSelf<_>>(linker, |state| state)
+}
+
+pub(crate) use wit::crossroads::request::Host as Request;
+pub(crate) use wit::crossroads::types::{Host, Resolution, Response};
+
+impl From<Resolution> for super::Resolution {
+    fn from(resolution: Resolution) -> Self {
+        match resolution {
+            Resolution::Forward => super::Resolution::Forward,
+            Resolution::Respond(Response { status_code, body }) => {
+                super::Resolution::Respond(super::Response {
+                    status_code,
+                    headers: Vec::new(),
+                    body,
+                })
+            }
+        }
+    }
+}
@@ -0,0 +1,152 @@
+use anyhow::{Context as _, Result};
+use sha2::{Digest, Sha256};
+use std::hash::{Hash, Hasher};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use wasmtime::Engine;
+use wasmtime::component::Component;
+
+use crate::configuration::cache::Configuration;
+
+const EXTENSION: &str = "cwasm";
+const PARTIAL_EXTENSION: &str = "partial";
+/// Age after which an artifact still being written is taken as abandoned.
+const PARTIAL_MAX_AGE: Duration = Duration::from_secs(60 * 60);
+
+/// Precompiled components on disk, keyed by the digest of the component and
+/// a hash of the engine's version and settings.
+pub(crate) struct ArtifactCache {
+    directory: PathBuf,
+    engine_hash: String,
+}
+
+impl ArtifactCache {
+    /// Opens the cache and removes artifacts of other engine versions as well
+    /// as ones abandoned while being written.
+    pub(crate) fn new(engine: &Engine, configuration: &Configuration) -> Result<Self> {
+        let directory = configuration.directory.clone();
+        std::fs::create_dir_all(&directory)
+            .with_context(|| format!("Failed to create cache directory {:?}", directory))?;
+        let mut hasher = Sha256Hasher::default();
+        engine.precompile_compatibility_hash().hash(&mut hasher);
+        let cache = Self {
+            directory,
+            engine_hash: hasher.digest(),
+        };
+        cache.prune()?;
+        Ok(cache)
+    }
+
+    /// Deserializes the cached artifact, compiling and storing it on a miss.
+    pub(crate) fn load(&self, engine: &Engine, bytes: &[u8]) -> Result<Component> {
+        let path = self.path(bytes);
+        if path.is_file() {
+            // Safety: the cache directory only contains artifacts serialized
+            // by this engine configuration, incompatible ones are rejected.
+            match unsafe { Component::deserialize_file(engine, &path) } {
+                Ok(component) => return Ok(component),
+                Err(error) => tracing::warn!(?path, %error, "Discarding cached component"),
+            }
+        }
+
+        let component = Component::from_binary(engine, bytes)?;
+        if let Err(error) = self.store(&component, &path) {
+            tracing::warn!(?path, %error, "Failed to cache component");
+        }
+        Ok(component)
+    }
+
+    /// Stores the artifact of a component compiled outside the cache, unless
+    /// there is one already.
+    pub(crate) fn insert(&self, bytes: &[u8], component: &Component) {
+        let path = self.path(bytes);
+        if path.is_file() {
+            return;
+        }
+        if let Err(error) = self.store(component, &path) {
+            tracing::warn!(?path, %error, "Failed to cache component");
+        }
+    }
+
+    /// Removes the artifact of the component, if there is one.
+    pub(crate) fn remove(&self, bytes: &[u8]) -> Result<()> {
+        let path = self.path(bytes);
+        match std::fs::remove_file(&path) {
+            Ok(()) => Ok(()),
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(error) => Err(error).with_context(|| format!("Failed to remove {:?}", path)),
+        }
+    }
+
+    fn path(&self, bytes: &[u8]) -> PathBuf {
+        let digest = format!("{:x}", Sha256::digest(bytes));
+        self.directory
+            .join(format!("{}-{}.{}", digest, self.engine_hash, EXTENSION))
+    }
+
+    /// Writes the artifact to a file of its own first, so processes sharing
+    /// the directory never see or clobber one that is partly written.
+    fn store(&self, component: &Component, path: &Path) -> Result<()> {
+        let artifact = component.serialize()?;
+        let mut partial = tempfile::Builder::new()
+            .suffix(&format!(".{}", PARTIAL_EXTENSION))
+            .tempfile_in(&self.directory)?;
+        partial.write_all(&artifact)?;
+        partial.persist(path)?;
+        Ok(())
+    }
+
+    fn prune(&self) -> Result<()> {
+        let suffix = format!("-{}.{}", self.engine_hash, EXTENSION);
+        for entry in std::fs::read_dir(&self.directory)? {
+            let path = entry?.path();
+            let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+                continue;
+            };
+            let stale = if name.ends_with(PARTIAL_EXTENSION) {
+                abandoned(&path)
+            } else {
+                name.ends_with(EXTENSION) && !name.ends_with(&suffix)
+            };
+            if stale {
+                std::fs::remove_file(&path)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Whether the partial artifact was last written longer ago than another
+/// process would take to finish it.
+fn abandoned(path: &Path) -> bool {
+    std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+        .is_some_and(|age| age > PARTIAL_MAX_AGE)
+}
+
+/// Feeds a `Hash` into SHA-256, which unlike the standard library's hasher
+/// is stable across builds and wide enough to key artifacts by.
+#[derive(Default)]
+struct Sha256Hasher(Sha256);
+
+impl Sha256Hasher {
+    fn digest(self) -> String {
+        format!("{:x}", self.0.finalize())
+    }
+}
+
+impl Hasher for Sha256Hasher {
+    fn write(&mut self, bytes: &[u8]) {
+        self.0.update(bytes);
+    }
+
+    fn finish(&self) -> u64 {
+        let digest = self.0.clone().finalize();
+        let mut prefix = [0; 8];
+        prefix.copy_from_slice(&digest[..8]);
+        u64::from_be_bytes(prefix)
+    }
+}
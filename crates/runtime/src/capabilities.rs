@@ -0,0 +1,72 @@
+use anyhow::{Context as _, Result, bail};
+use std::path::PathBuf;
+use wasmtime_wasi::{DirPerms, FilePerms, WasiCtx, WasiCtxBuilder};
+
+use crate::configuration::capabilities::{Configuration, Stdio};
+
+/// Capabilities of a proxy, resolved once when it is loaded and shared by
+/// every store serving it.
+#[derive(Debug)]
+pub(crate) struct Capabilities {
+    env: Vec<(String, String)>,
+    preopens: Vec<(PathBuf, String)>,
+    stdio: Stdio,
+    network: bool,
+}
+
+impl Capabilities {
+    /// Reads the granted environment variables and checks that every
+    /// preopened directory can be read, so a missing one fails the load
+    /// instead of each request.
+    pub(crate) fn resolve(configuration: &Configuration) -> Result<Self> {
+        let env = configuration
+            .env
+            .iter()
+            .filter_map(|name| Some((name.clone(), std::env::var(name).ok()?)))
+            .collect();
+        let preopens = configuration
+            .preopens
+            .iter()
+            .map(|preopen| {
+                let host = std::fs::canonicalize(&preopen.host)
+                    .with_context(|| format!("Failed to preopen {:?}", preopen.host))?;
+                if !host.is_dir() {
+                    bail!("Failed to preopen {:?}: not a directory", preopen.host);
+                }
+                std::fs::read_dir(&host)
+                    .with_context(|| format!("Failed to preopen {:?}", preopen.host))?;
+                Ok((host, preopen.guest.clone()))
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self {
+            env,
+            preopens,
+            stdio: configuration.stdio,
+            network: configuration.network,
+        })
+    }
+
+    /// Grants the component exactly these capabilities, no arguments of the
+    /// gateway are passed on.
+    pub(crate) fn wasi_context(&self) -> Result<WasiCtx> {
+        let mut builder = WasiCtxBuilder::new();
+        builder.envs(&self.env);
+        for (host, guest) in &self.preopens {
+            builder
+                .preopened_dir(host, guest, DirPerms::READ, FilePerms::READ)
+                .with_context(|| format!("Failed to preopen {:?}", host))?;
+        }
+        if self.stdio == Stdio::Inherit {
+            builder.inherit_stdout().inherit_stderr();
+        }
+        if self.network {
+            builder.inherit_network().allow_ip_name_lookup(true);
+        } else {
+            builder
+                .allow_tcp(false)
+                .allow_udp(false)
+                .allow_ip_name_lookup(false);
+        }
+        Ok(builder.build())
+    }
+}
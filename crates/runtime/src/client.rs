@@ -0,0 +1,116 @@
+use rama::http::client::EasyHttpWebClient;
+use rama::http::dep::http_body_util::{BodyExt, Limited};
+use rama::http::{Request as RamaRequest, Response as RamaResponse, Uri};
+use rama::{Context, Service};
+use std::fmt;
+use std::time::Duration;
+
+use crate::configuration::client::Configuration;
+
+/// Outbound HTTP client handed to a single guest invocation.
+pub struct Client {
+    configuration: Configuration,
+    calls: u32,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Error {
+    HostNotAllowed(String),
+    BudgetExhausted,
+    Timeout,
+    Failed(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::HostNotAllowed(host) => write!(f, "Host {} is not allowed", host),
+            Error::BudgetExhausted => write!(f, "Call budget is exhausted"),
+            Error::Timeout => write!(f, "Call timed out"),
+            Error::Failed(error) => write!(f, "Call failed: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl Client {
+    pub fn new(configuration: Configuration) -> Self {
+        Self {
+            configuration,
+            calls: 0,
+        }
+    }
+
+    /// Sends the request and buffers the response body.
+    pub async fn send(&mut self, request: RamaRequest) -> Result<RamaResponse<Vec<u8>>, Error> {
+        if !self.is_allowed(request.uri()) {
+            return Err(Error::HostNotAllowed(authority(request.uri())));
+        }
+        if self.calls >= self.configuration.max_calls {
+            return Err(Error::BudgetExhausted);
+        }
+        self.calls += 1;
+
+        let timeout = Duration::from_millis(self.configuration.timeout_ms);
+        let limit = self.configuration.max_response_bytes;
+        let call = async {
+            let response = EasyHttpWebClient::default()
+                .serve(Context::default(), request)
+                .await
+                .map_err(|e| Error::Failed(e.to_string()))?;
+            let (parts, body) = response.into_parts();
+            let body = Limited::new(body, limit)
+                .collect()
+                .await
+                .map_err(|e| Error::Failed(e.to_string()))?
+                .to_bytes();
+            Ok(RamaResponse::from_parts(parts, body.to_vec()))
+        };
+        tokio::time::timeout(timeout, call)
+            .await
+            .map_err(|_| Error::Timeout)?
+    }
+
+    /// Whether host and port of `uri` are on the allowlist. Entries without a
+    /// port only allow the default port of the scheme. Host names are compared
+    /// case-insensitively, for wildcards as well.
+    pub fn is_allowed(&self, uri: &Uri) -> bool {
+        let host = uri.host().unwrap_or_default().to_ascii_lowercase();
+        let port = port(uri);
+        self.configuration.allowed_hosts.iter().any(|allowed| {
+            let allowed = allowed.to_ascii_lowercase();
+            let (allowed, allowed_port) = split_port(&allowed);
+            let host_matches = match allowed.strip_prefix("*.") {
+                Some(domain) => host
+                    .strip_suffix(domain)
+                    .is_some_and(|subdomain| subdomain.ends_with('.')),
+                None => allowed == host,
+            };
+            host_matches && allowed_port.unwrap_or_else(|| default_port(uri)) == port
+        })
+    }
+}
+
+/// Splits an allowlist entry into its host and its port, if it has one.
+fn split_port(entry: &str) -> (&str, Option<u16>) {
+    entry
+        .rsplit_once(':')
+        .and_then(|(host, port)| Some((host, Some(port.parse().ok()?))))
+        .unwrap_or((entry, None))
+}
+
+fn port(uri: &Uri) -> u16 {
+    uri.port_u16().unwrap_or_else(|| default_port(uri))
+}
+
+fn default_port(uri: &Uri) -> u16 {
+    match uri.scheme_str() {
+        Some("https") => 443,
+        _ => 80,
+    }
+}
+
+fn authority(uri: &Uri) -> String {
+    format!("{}:{}", uri.host().unwrap_or_default(), port(uri))
+}
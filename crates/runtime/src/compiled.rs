@@ -0,0 +1,39 @@
+use sha2::{Digest, Sha256};
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use wasmtime::component::Component;
+
+/// Validated components kept until they are loaded.
+const MAX_COMPILED: usize = 8;
+
+/// Components compiled while validating an upload, so loading them to serve
+/// requests does not compile them again. Only the latest ones are kept.
+#[derive(Default)]
+pub(crate) struct Compiled {
+    components: Mutex<VecDeque<(String, Component)>>,
+}
+
+impl Compiled {
+    pub(crate) fn insert(&self, bytes: &[u8], component: Component) {
+        let Ok(mut components) = self.components.lock() else {
+            return;
+        };
+        let digest = digest(bytes);
+        components.retain(|(other, _)| *other != digest);
+        if components.len() == MAX_COMPILED {
+            components.pop_front();
+        }
+        components.push_back((digest, component));
+    }
+
+    pub(crate) fn take(&self, bytes: &[u8]) -> Option<Component> {
+        let mut components = self.components.lock().ok()?;
+        let digest = digest(bytes);
+        let index = components.iter().position(|(other, _)| *other == digest)?;
+        components.remove(index).map(|(_, component)| component)
+    }
+}
+
+fn digest(bytes: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(bytes))
+}
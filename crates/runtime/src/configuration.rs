@@ -0,0 +1,87 @@
+pub mod cache;
+pub mod capabilities;
+pub mod client;
+pub mod fallback;
+pub mod limits;
+pub mod logging;
+pub mod pooling;
+pub mod proxy;
+pub mod rollback;
+mod validation;
+
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Default, serde::Deserialize, garde::Validate)]
+pub struct Configuration {
+    #[garde(dive)]
+    #[serde(default)]
+    pub capabilities: capabilities::Configuration,
+    #[garde(dive)]
+    #[serde(default)]
+    pub client: client::Configuration,
+    #[garde(dive)]
+    #[serde(default)]
+    pub limits: limits::Configuration,
+    #[garde(skip)]
+    #[serde(default)]
+    pub log_level: logging::Level,
+    /// Stores precompiled components on disk when set.
+    #[garde(dive)]
+    #[serde(default)]
+    pub cache: Option<cache::Configuration>,
+    /// Enables the pooling instance allocator when set.
+    #[garde(dive, custom(validation::fits_pooled_memory(&self.limits, &self.proxies)))]
+    #[serde(default)]
+    pub pooling: Option<pooling::Configuration>,
+    /// Served in place of a component that trapped, written as a map like
+    /// `response: {status_code: 503}`.
+    #[garde(dive)]
+    #[serde(default, with = "serde_yaml::with::singleton_map")]
+    pub fallback: Option<fallback::Configuration>,
+    /// Rolls back to the previous proxy when set.
+    #[garde(dive)]
+    #[serde(default)]
+    pub rollback: Option<rollback::Configuration>,
+    /// Overrides of the global settings keyed by proxy tag.
+    #[garde(dive)]
+    #[serde(default)]
+    pub proxies: HashMap<String, proxy::Configuration>,
+}
+
+impl Configuration {
+    pub fn capabilities(&self, tag: Option<&str>) -> &capabilities::Configuration {
+        self.proxy(tag)
+            .and_then(|proxy| proxy.capabilities.as_ref())
+            .unwrap_or(&self.capabilities)
+    }
+
+    pub fn client(&self, tag: Option<&str>) -> &client::Configuration {
+        self.proxy(tag)
+            .and_then(|proxy| proxy.client.as_ref())
+            .unwrap_or(&self.client)
+    }
+
+    pub fn limits(&self, tag: Option<&str>) -> &limits::Configuration {
+        self.proxy(tag)
+            .and_then(|proxy| proxy.limits.as_ref())
+            .unwrap_or(&self.limits)
+    }
+
+    pub fn log_level(&self, tag: Option<&str>) -> logging::Level {
+        self.proxy(tag)
+            .and_then(|proxy| proxy.log_level)
+            .unwrap_or(self.log_level)
+    }
+
+    /// Tag of the proxy serving requests in place of one that trapped.
+    pub fn fallback_tag(&self) -> Option<&str> {
+        match &self.fallback {
+            Some(fallback::Configuration::Proxy(proxy)) => Some(&proxy.tag),
+            _ => None,
+        }
+    }
+
+    fn proxy(&self, tag: Option<&str>) -> Option<&proxy::Configuration> {
+        tag.and_then(|tag| self.proxies.get(tag))
+    }
+}
@@ -0,0 +1,8 @@
+use std::path::PathBuf;
+
+#[derive(Debug, Clone, serde::Deserialize, garde::Validate)]
+pub struct Configuration {
+    /// Directory precompiled components are stored in, created if missing.
+    #[garde(skip)]
+    pub directory: PathBuf,
+}
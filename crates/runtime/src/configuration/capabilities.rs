@@ -0,0 +1,42 @@
+use std::path::PathBuf;
+
+/// WASI capabilities granted to a component, everything not listed is denied.
+#[derive(Debug, Clone, Default, serde::Deserialize, garde::Validate)]
+pub struct Configuration {
+    /// Environment variables of the gateway passed on by name, unset ones are
+    /// skipped.
+    #[garde(inner(length(min = 1)))]
+    #[serde(default)]
+    pub env: Vec<String>,
+    /// Directories mounted read-only.
+    #[garde(dive)]
+    #[serde(default)]
+    pub preopens: Vec<Preopen>,
+    #[garde(skip)]
+    #[serde(default)]
+    pub stdio: Stdio,
+    /// Grants access to TCP and UDP sockets and name lookups, outbound HTTP
+    /// through the `client` interface is configured separately.
+    #[garde(skip)]
+    #[serde(default)]
+    pub network: bool,
+}
+
+#[derive(Debug, Clone, serde::Deserialize, garde::Validate)]
+pub struct Preopen {
+    #[garde(skip)]
+    pub host: PathBuf,
+    /// Path the directory is visible at inside the component.
+    #[garde(length(min = 1))]
+    pub guest: String,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Stdio {
+    /// Reads see end of file and writes are discarded.
+    #[default]
+    Null,
+    /// Writes go to the gateway's stdout and stderr, stdin is never shared.
+    Inherit,
+}
@@ -0,0 +1,42 @@
+#[derive(Debug, Clone, serde::Deserialize, garde::Validate)]
+pub struct Configuration {
+    /// Hosts components may call as `host` or `host:port`, `*.example.com`
+    /// also matches subdomains. Without a port only the default one of the
+    /// scheme is allowed.
+    #[garde(inner(length(min = 1)))]
+    #[serde(default)]
+    pub allowed_hosts: Vec<String>,
+    #[garde(range(min = 1))]
+    #[serde(default = "default_timeout_ms")]
+    pub timeout_ms: u64,
+    /// Maximum number of calls a component may make per request.
+    #[garde(skip)]
+    #[serde(default = "default_max_calls")]
+    pub max_calls: u32,
+    #[garde(range(min = 1))]
+    #[serde(default = "default_max_response_bytes")]
+    pub max_response_bytes: usize,
+}
+
+impl Default for Configuration {
+    fn default() -> Self {
+        Self {
+            allowed_hosts: Vec::new(),
+            timeout_ms: default_timeout_ms(),
+            max_calls: default_max_calls(),
+            max_response_bytes: default_max_response_bytes(),
+        }
+    }
+}
+
+fn default_timeout_ms() -> u64 {
+    5000
+}
+
+fn default_max_calls() -> u32 {
+    10
+}
+
+fn default_max_response_bytes() -> usize {
+    1024 * 1024
+}
@@ -0,0 +1,29 @@
+/// What is served in place of a component that trapped.
+#[derive(Debug, Clone, serde::Deserialize, garde::Validate)]
+#[serde(rename_all = "lowercase")]
+pub enum Configuration {
+    /// Serves the request with another stored proxy.
+    Proxy(#[garde(dive)] Proxy),
+    /// Serves a static response.
+    Response(#[garde(dive)] Response),
+}
+
+#[derive(Debug, Clone, serde::Deserialize, garde::Validate)]
+pub struct Proxy {
+    #[garde(length(min = 1))]
+    pub tag: String,
+}
+
+#[derive(Debug, Clone, serde::Deserialize, garde::Validate)]
+pub struct Response {
+    #[garde(range(min = 100, max = 599))]
+    #[serde(default = "default_status_code")]
+    pub status_code: u16,
+    #[garde(skip)]
+    #[serde(default)]
+    pub body: String,
+}
+
+fn default_status_code() -> u16 {
+    503
+}
@@ -0,0 +1,47 @@
+#[derive(Debug, Clone, serde::Deserialize, garde::Validate)]
+pub struct Configuration {
+    /// Time a single guest invocation may spend executing guest code, in
+    /// steps of 10 ms. Time spent in host calls does not count.
+    #[garde(range(min = 1))]
+    #[serde(default = "default_execution_timeout_ms")]
+    pub execution_timeout_ms: u64,
+    /// Linear memory a single component instance may grow to.
+    #[garde(range(min = 1))]
+    #[serde(default = "default_max_memory_bytes")]
+    pub max_memory_bytes: usize,
+    #[garde(range(min = 1))]
+    #[serde(default = "default_max_table_elements")]
+    pub max_table_elements: usize,
+    /// Request or response body the host buffers for a guest, larger limits
+    /// passed by the guest are lowered to it.
+    #[garde(range(min = 1))]
+    #[serde(default = "default_max_body_bytes")]
+    pub max_body_bytes: u64,
+}
+
+impl Default for Configuration {
+    fn default() -> Self {
+        Self {
+            execution_timeout_ms: default_execution_timeout_ms(),
+            max_memory_bytes: default_max_memory_bytes(),
+            max_table_elements: default_max_table_elements(),
+            max_body_bytes: default_max_body_bytes(),
+        }
+    }
+}
+
+fn default_execution_timeout_ms() -> u64 {
+    100
+}
+
+fn default_max_memory_bytes() -> usize {
+    64 * 1024 * 1024
+}
+
+fn default_max_table_elements() -> usize {
+    10_000
+}
+
+fn default_max_body_bytes() -> u64 {
+    16 * 1024 * 1024
+}
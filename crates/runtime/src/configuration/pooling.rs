@@ -0,0 +1,53 @@
+/// Sizing of wasmtime's pooling instance allocator, which reserves memory
+/// for instances up front instead of allocating it per request.
+#[derive(Debug, Clone, serde::Deserialize, garde::Validate)]
+pub struct Configuration {
+    #[garde(range(min = 1))]
+    #[serde(default = "default_total_component_instances")]
+    pub total_component_instances: u32,
+    #[garde(range(min = 1))]
+    #[serde(default = "default_total_core_instances")]
+    pub total_core_instances: u32,
+    #[garde(range(min = 1))]
+    #[serde(default = "default_total_memories")]
+    pub total_memories: u32,
+    #[garde(range(min = 1))]
+    #[serde(default = "default_total_tables")]
+    pub total_tables: u32,
+    /// Upper bound of a single linear memory, has to cover `max_memory_bytes`.
+    #[garde(range(min = 1))]
+    #[serde(default = "default_max_memory_size")]
+    pub max_memory_size: usize,
+}
+
+impl Default for Configuration {
+    fn default() -> Self {
+        Self {
+            total_component_instances: default_total_component_instances(),
+            total_core_instances: default_total_core_instances(),
+            total_memories: default_total_memories(),
+            total_tables: default_total_tables(),
+            max_memory_size: default_max_memory_size(),
+        }
+    }
+}
+
+fn default_total_component_instances() -> u32 {
+    1000
+}
+
+fn default_total_core_instances() -> u32 {
+    10_000
+}
+
+fn default_total_memories() -> u32 {
+    2000
+}
+
+fn default_total_tables() -> u32 {
+    2000
+}
+
+fn default_max_memory_size() -> usize {
+    64 * 1024 * 1024
+}
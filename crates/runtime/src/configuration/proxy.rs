@@ -0,0 +1,17 @@
+use super::{capabilities, client, limits, logging};
+
+#[derive(Debug, Clone, Default, serde::Deserialize, garde::Validate)]
+pub struct Configuration {
+    #[garde(dive)]
+    #[serde(default)]
+    pub capabilities: Option<capabilities::Configuration>,
+    #[garde(dive)]
+    #[serde(default)]
+    pub client: Option<client::Configuration>,
+    #[garde(dive)]
+    #[serde(default)]
+    pub limits: Option<limits::Configuration>,
+    #[garde(skip)]
+    #[serde(default)]
+    pub log_level: Option<logging::Level>,
+}
@@ -0,0 +1,27 @@
+#[derive(Debug, Clone, serde::Deserialize, garde::Validate)]
+pub struct Configuration {
+    /// Traps of a single proxy within the window that trigger a rollback.
+    #[garde(range(min = 1))]
+    #[serde(default = "default_max_traps")]
+    pub max_traps: usize,
+    #[garde(range(min = 1))]
+    #[serde(default = "default_window_ms")]
+    pub window_ms: u64,
+}
+
+impl Default for Configuration {
+    fn default() -> Self {
+        Self {
+            max_traps: default_max_traps(),
+            window_ms: default_window_ms(),
+        }
+    }
+}
+
+fn default_max_traps() -> usize {
+    5
+}
+
+fn default_window_ms() -> u64 {
+    60_000
+}
@@ -0,0 +1,32 @@
+use std::collections::HashMap;
+
+use super::{limits, pooling, proxy};
+
+/// Instances cannot grow beyond the memory reserved by the pooling
+/// allocator, so no memory limit may exceed it.
+pub(super) fn fits_pooled_memory<'a>(
+    limits: &'a limits::Configuration,
+    proxies: &'a HashMap<String, proxy::Configuration>,
+) -> impl FnOnce(&Option<pooling::Configuration>, &()) -> garde::Result + 'a {
+    move |pooling, _| {
+        let Some(pooling) = pooling else {
+            return Ok(());
+        };
+        let mut exceeding = proxies
+            .iter()
+            .filter_map(|(tag, proxy)| Some((tag.as_str(), proxy.limits.as_ref()?)))
+            .chain(std::iter::once(("global", limits)))
+            .filter(|(_, limits)| limits.max_memory_bytes > pooling.max_memory_size)
+            .map(|(tag, _)| tag)
+            .collect::<Vec<_>>();
+        if exceeding.is_empty() {
+            return Ok(());
+        }
+        exceeding.sort();
+        Err(garde::Error::new(format!(
+            "max_memory_size of {} bytes is below max_memory_bytes of: {}",
+            pooling.max_memory_size,
+            exceeding.join(", ")
+        )))
+    }
+}
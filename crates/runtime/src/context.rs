@@ -1,15 +1,35 @@
+pub(crate) mod message;
+mod v0_1;
+
 use anyhow::Result;
-use rama::http::Request as RamaRequest;
+use rama::http::{Body, Request as RamaRequest, Response as RamaResponse};
+use std::collections::HashMap;
 use std::str::FromStr;
+use std::sync::Arc;
 use wasmtime::component::ResourceTable;
-use wasmtime_wasi::{WasiCtx, WasiCtxBuilder, WasiCtxView, WasiView};
+use wasmtime_wasi::{WasiCtx, WasiCtxView, WasiView};
 
-use super::bindings::{Host, Request};
+use super::bindings::{
+    Client, ClientError, Config, Host, KeyValue, Level, Logging, Method, OutgoingRequest, Request,
+    Response, UpstreamResponse,
+};
+use crate::capabilities::Capabilities;
+use crate::client;
+use crate::configuration::logging;
+use crate::kv::Namespace;
+use crate::limiter::Limiter;
+use crate::logging::Logger;
 
 pub struct Context {
     pub wasi: WasiCtx,
     pub table: ResourceTable,
     pub request: RamaRequest,
+    pub response: RamaResponse,
+    pub client: client::Client,
+    pub kv: Namespace,
+    pub configuration: Arc<HashMap<String, String>>,
+    pub(crate) logger: Logger,
+    pub(crate) limiter: Limiter,
 }
 
 impl WasiView for Context {
@@ -22,43 +42,238 @@ impl WasiView for Context {
 }
 
 impl Context {
-    pub fn new(request: RamaRequest) -> Self {
-        Context {
-            wasi: WasiCtxBuilder::new().inherit_stdio().inherit_args().build(),
+    pub fn new(
+        request: RamaRequest,
+        client: client::Client,
+        kv: Namespace,
+        configuration: Arc<HashMap<String, String>>,
+        logger: Logger,
+        limiter: Limiter,
+        capabilities: &Capabilities,
+    ) -> Result<Self> {
+        let context = Context {
+            wasi: capabilities.wasi_context()?,
             table: ResourceTable::new(),
             request,
-        }
+            response: RamaResponse::default(),
+            client,
+            kv,
+            configuration,
+            logger,
+            limiter,
+        };
+        Ok(context)
     }
 }
 
 impl Host for Context {}
 
 impl Request for Context {
-    fn headers(&mut self) -> Vec<(String, String)> {
-        self.request
-            .headers()
-            .iter()
-            .map(|(key, value)| (key.to_string(), value.to_str().unwrap().to_string()))
-            .collect()
+    async fn headers(&mut self) -> Vec<(String, Vec<u8>)> {
+        message::headers(&self.request)
     }
 
-    fn set_header(&mut self, key: String, value: String) -> Result<(), String> {
-        let key = http::HeaderName::from_bytes(key.as_bytes()).map_err(|e| e.to_string())?;
-        let value = http::HeaderValue::from_bytes(value.as_bytes()).map_err(|e| e.to_string())?;
-        self.request
-            .headers_mut()
-            .insert(key, value)
-            .map(|_| ())
-            .ok_or_else(|| "Failed to insert header".to_string())
+    async fn header(&mut self, name: String) -> Vec<Vec<u8>> {
+        message::header(&self.request, name)
     }
 
-    fn uri(&mut self) -> String {
+    async fn set_header(&mut self, name: String, value: Vec<u8>) -> Result<(), String> {
+        message::set_header(&mut self.request, name, value)
+    }
+
+    async fn append_header(&mut self, name: String, value: Vec<u8>) -> Result<(), String> {
+        message::append_header(&mut self.request, name, value)
+    }
+
+    async fn remove_header(&mut self, name: String) -> Result<(), String> {
+        message::remove_header(&mut self.request, name)
+    }
+
+    async fn method(&mut self) -> Method {
+        match *self.request.method() {
+            http::Method::GET => Method::Get,
+            http::Method::HEAD => Method::Head,
+            http::Method::POST => Method::Post,
+            http::Method::PUT => Method::Put,
+            http::Method::DELETE => Method::Delete,
+            http::Method::CONNECT => Method::Connect,
+            http::Method::OPTIONS => Method::Options,
+            http::Method::TRACE => Method::Trace,
+            http::Method::PATCH => Method::Patch,
+            ref method => Method::Other(method.to_string()),
+        }
+    }
+
+    async fn set_method(&mut self, method: Method) -> Result<(), String> {
+        *self.request.method_mut() = into_http_method(method)?;
+        Ok(())
+    }
+
+    async fn uri(&mut self) -> String {
         self.request.uri().to_string()
     }
 
-    fn set_uri(&mut self, uri: String) -> Result<(), String> {
+    async fn set_uri(&mut self, uri: String) -> Result<(), String> {
         http::Uri::from_str(&uri)
             .map(|u| *self.request.uri_mut() = u)
-            .map_err(|e| format!("Could not create uri {}: {}", uri, e.to_string()))
+            .map_err(|e| format!("Could not create uri {}: {}", uri, e))
+    }
+
+    async fn body(&mut self, limit: u64) -> Result<Vec<u8>, String> {
+        let limit = self.limiter.body_limit(limit);
+        message::body(&mut self.request, limit).await
+    }
+
+    async fn read_body_chunk(&mut self) -> Result<Option<Vec<u8>>, String> {
+        message::read_body_chunk(&mut self.request).await
+    }
+
+    async fn set_body(&mut self, body: Vec<u8>) {
+        message::set_body(&mut self.request, body)
+    }
+}
+
+impl UpstreamResponse for Context {
+    async fn status(&mut self) -> u16 {
+        self.response.status().as_u16()
+    }
+
+    async fn set_status(&mut self, status: u16) -> Result<(), String> {
+        http::StatusCode::from_u16(status)
+            .map(|s| *self.response.status_mut() = s)
+            .map_err(|e| format!("Could not create status {}: {}", status, e))
+    }
+
+    async fn headers(&mut self) -> Vec<(String, Vec<u8>)> {
+        message::headers(&self.response)
+    }
+
+    async fn header(&mut self, name: String) -> Vec<Vec<u8>> {
+        message::header(&self.response, name)
+    }
+
+    async fn set_header(&mut self, name: String, value: Vec<u8>) -> Result<(), String> {
+        message::set_header(&mut self.response, name, value)
+    }
+
+    async fn append_header(&mut self, name: String, value: Vec<u8>) -> Result<(), String> {
+        message::append_header(&mut self.response, name, value)
     }
+
+    async fn remove_header(&mut self, name: String) -> Result<(), String> {
+        message::remove_header(&mut self.response, name)
+    }
+
+    async fn body(&mut self, limit: u64) -> Result<Vec<u8>, String> {
+        let limit = self.limiter.body_limit(limit);
+        message::body(&mut self.response, limit).await
+    }
+
+    async fn read_body_chunk(&mut self) -> Result<Option<Vec<u8>>, String> {
+        message::read_body_chunk(&mut self.response).await
+    }
+
+    async fn set_body(&mut self, body: Vec<u8>) {
+        message::set_body(&mut self.response, body)
+    }
+}
+
+impl Client for Context {
+    async fn send(&mut self, request: OutgoingRequest) -> Result<Response, ClientError> {
+        let OutgoingRequest {
+            method,
+            uri,
+            headers,
+            body,
+        } = request;
+        let method = into_http_method(method).map_err(ClientError::InvalidRequest)?;
+        let builder = RamaRequest::builder().method(method).uri(uri);
+        let builder = headers
+            .into_iter()
+            .fold(builder, |builder, (key, value)| builder.header(key, value));
+        let request = builder
+            .body(body.map(Body::from).unwrap_or(Body::empty()))
+            .map_err(|e| ClientError::InvalidRequest(e.to_string()))?;
+
+        let response = self.client.send(request).await.map_err(|e| match e {
+            client::Error::HostNotAllowed(host) => ClientError::HostNotAllowed(host),
+            client::Error::BudgetExhausted => ClientError::BudgetExhausted,
+            client::Error::Timeout => ClientError::Timeout,
+            client::Error::Failed(error) => ClientError::Failed(error),
+        })?;
+        let (parts, body) = response.into_parts();
+        Ok(Response {
+            status_code: parts.status.as_u16(),
+            headers: parts
+                .headers
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.as_bytes().to_vec()))
+                .collect(),
+            body: Some(body),
+        })
+    }
+}
+
+impl KeyValue for Context {
+    async fn get(&mut self, key: String) -> Result<Option<Vec<u8>>, String> {
+        self.kv.get(&key).await.map_err(|e| e.to_string())
+    }
+
+    async fn set(&mut self, key: String, value: Vec<u8>) -> Result<(), String> {
+        self.kv.set(&key, value).await.map_err(|e| e.to_string())
+    }
+
+    async fn delete(&mut self, key: String) -> Result<(), String> {
+        self.kv.delete(&key).await.map_err(|e| e.to_string())
+    }
+
+    async fn increment(&mut self, key: String, delta: i64) -> Result<i64, String> {
+        self.kv
+            .increment(&key, delta)
+            .await
+            .map_err(|e| e.to_string())
+    }
+}
+
+impl Config for Context {
+    async fn get(&mut self, key: String) -> Option<String> {
+        self.configuration.get(&key).cloned()
+    }
+
+    async fn get_all(&mut self) -> Vec<(String, String)> {
+        self.configuration
+            .iter()
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect()
+    }
+}
+
+impl Logging for Context {
+    async fn log(&mut self, level: Level, message: String, fields: Vec<(String, String)>) {
+        let level = match level {
+            Level::Trace => logging::Level::Trace,
+            Level::Debug => logging::Level::Debug,
+            Level::Info => logging::Level::Info,
+            Level::Warn => logging::Level::Warn,
+            Level::Error => logging::Level::Error,
+        };
+        self.logger.log(level, &message, &fields);
+    }
+}
+
+fn into_http_method(method: Method) -> Result<http::Method, String> {
+    let method = match method {
+        Method::Get => http::Method::GET,
+        Method::Head => http::Method::HEAD,
+        Method::Post => http::Method::POST,
+        Method::Put => http::Method::PUT,
+        Method::Delete => http::Method::DELETE,
+        Method::Connect => http::Method::CONNECT,
+        Method::Options => http::Method::OPTIONS,
+        Method::Trace => http::Method::TRACE,
+        Method::Patch => http::Method::PATCH,
+        Method::Other(method) => http::Method::from_bytes(method.as_bytes())
+            .map_err(|e| format!("Could not create method {}: {}", method, e))?,
+    };
+    Ok(method)
 }
@@ -0,0 +1,264 @@
+use bytes::Bytes;
+use rama::error::BoxError;
+use rama::http::dep::http_body::{self, Frame, SizeHint};
+use rama::http::dep::http_body_util::BodyExt;
+use rama::http::{Body, HeaderMap, Request as RamaRequest, Response as RamaResponse};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+
+/// Request and response share the header and body handling exposed to guests.
+pub(crate) trait Message {
+    fn headers(&self) -> &HeaderMap;
+    fn headers_mut(&mut self) -> &mut HeaderMap;
+    fn body_mut(&mut self) -> &mut Body;
+}
+
+impl Message for RamaRequest {
+    fn headers(&self) -> &HeaderMap {
+        RamaRequest::headers(self)
+    }
+
+    fn headers_mut(&mut self) -> &mut HeaderMap {
+        RamaRequest::headers_mut(self)
+    }
+
+    fn body_mut(&mut self) -> &mut Body {
+        RamaRequest::body_mut(self)
+    }
+}
+
+impl Message for RamaResponse {
+    fn headers(&self) -> &HeaderMap {
+        RamaResponse::headers(self)
+    }
+
+    fn headers_mut(&mut self) -> &mut HeaderMap {
+        RamaResponse::headers_mut(self)
+    }
+
+    fn body_mut(&mut self) -> &mut Body {
+        RamaResponse::body_mut(self)
+    }
+}
+
+pub(crate) fn headers(message: &impl Message) -> Vec<(String, Vec<u8>)> {
+    message
+        .headers()
+        .iter()
+        .map(|(key, value)| (key.to_string(), value.as_bytes().to_vec()))
+        .collect()
+}
+
+pub(crate) fn header(message: &impl Message, name: String) -> Vec<Vec<u8>> {
+    message
+        .headers()
+        .get_all(name.as_str())
+        .iter()
+        .map(|value| value.as_bytes().to_vec())
+        .collect()
+}
+
+pub(crate) fn set_header(
+    message: &mut impl Message,
+    name: String,
+    value: Vec<u8>,
+) -> Result<(), String> {
+    let (name, value) = header_field(name, value)?;
+    message.headers_mut().insert(name, value);
+    Ok(())
+}
+
+pub(crate) fn append_header(
+    message: &mut impl Message,
+    name: String,
+    value: Vec<u8>,
+) -> Result<(), String> {
+    let (name, value) = header_field(name, value)?;
+    message.headers_mut().append(name, value);
+    Ok(())
+}
+
+pub(crate) fn remove_header(message: &mut impl Message, name: String) -> Result<(), String> {
+    let name = http::HeaderName::from_bytes(name.as_bytes())
+        .map_err(|e| format!("Invalid header name {}: {}", name, e))?;
+    message.headers_mut().remove(name);
+    Ok(())
+}
+
+fn header_field(
+    name: String,
+    value: Vec<u8>,
+) -> Result<(http::HeaderName, http::HeaderValue), String> {
+    let name = http::HeaderName::from_bytes(name.as_bytes())
+        .map_err(|e| format!("Invalid header name {}: {}", name, e))?;
+    let value = http::HeaderValue::from_bytes(&value)
+        .map_err(|e| format!("Invalid value for header {}: {}", name, e))?;
+    Ok((name, value))
+}
+
+/// Buffers the whole body up to `limit` bytes. A body found to be larger is
+/// put back together from the bytes read so far and the rest, so the message
+/// can still be forwarded as it came in.
+pub(crate) async fn body(message: &mut impl Message, limit: u64) -> Result<Vec<u8>, String> {
+    if content_length(message.headers()).is_some_and(|length| length > limit) {
+        return Err(format!("Body exceeds the limit of {} bytes", limit));
+    }
+    let mut buffered = Vec::new();
+    let result = loop {
+        match message.body_mut().frame().await {
+            None => break Ok(()),
+            Some(Err(e)) => break Err(format!("Could not read body: {}", e)),
+            Some(Ok(frame)) => {
+                if let Ok(data) = frame.into_data() {
+                    buffered.extend_from_slice(&data);
+                }
+                if buffered.len() as u64 > limit {
+                    break Err(format!("Body exceeds the limit of {} bytes", limit));
+                }
+            }
+        }
+    };
+    if let Err(error) = result {
+        let rest = std::mem::take(message.body_mut());
+        *message.body_mut() = Body::new(Prefixed {
+            read: Some(Bytes::from(buffered)),
+            rest,
+        });
+        return Err(error);
+    }
+    *message.body_mut() = Body::from(buffered.clone());
+    Ok(buffered)
+}
+
+/// Reads the next chunk, the length declared by the message shrinks by it so
+/// it still matches what is left to forward.
+pub(crate) async fn read_body_chunk(message: &mut impl Message) -> Result<Option<Vec<u8>>, String> {
+    while let Some(frame) = message.body_mut().frame().await {
+        let frame = frame.map_err(|e| format!("Could not read body: {}", e))?;
+        if let Ok(data) = frame.into_data() {
+            let headers = message.headers_mut();
+            let left =
+                content_length(headers).map(|length| length.saturating_sub(data.len() as u64));
+            headers.remove(http::header::TRANSFER_ENCODING);
+            headers.remove(http::header::CONTENT_LENGTH);
+            if let Some(left) = left {
+                headers.insert(http::header::CONTENT_LENGTH, http::HeaderValue::from(left));
+            }
+            return Ok(Some(data.to_vec()));
+        }
+    }
+    Ok(None)
+}
+
+pub(crate) fn set_body(message: &mut impl Message, body: Vec<u8>) {
+    let headers = message.headers_mut();
+    headers.remove(http::header::TRANSFER_ENCODING);
+    headers.insert(
+        http::header::CONTENT_LENGTH,
+        http::HeaderValue::from(body.len()),
+    );
+    *message.body_mut() = Body::from(body);
+}
+
+fn content_length(headers: &HeaderMap) -> Option<u64> {
+    headers
+        .get(http::header::CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse().ok())
+}
+
+/// Body whose first bytes were already read, they are yielded again before
+/// the rest of it.
+struct Prefixed {
+    read: Option<Bytes>,
+    rest: Body,
+}
+
+impl http_body::Body for Prefixed {
+    type Data = Bytes;
+    type Error = BoxError;
+
+    fn poll_frame(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        if let Some(read) = self.read.take() {
+            return Poll::Ready(Some(Ok(Frame::data(read))));
+        }
+        http_body::Body::poll_frame(Pin::new(&mut self.rest), cx).map_err(Into::into)
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.read.is_none() && http_body::Body::is_end_stream(&self.rest)
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        let read = self.read.as_ref().map_or(0, |read| read.len() as u64);
+        let rest = http_body::Body::size_hint(&self.rest);
+        let mut hint = SizeHint::new();
+        hint.set_lower(rest.lower() + read);
+        if let Some(upper) = rest.upper() {
+            hint.set_upper(upper + read);
+        }
+        hint
+    }
+}
+
+/// Body left in a slot shared with the runtime until it is first polled, the
+/// runtime can take it back for as long as nobody started reading it.
+pub(crate) struct Deferred {
+    slot: Arc<Mutex<Option<Body>>>,
+    body: Option<Body>,
+}
+
+impl Deferred {
+    pub(crate) fn new(slot: Arc<Mutex<Option<Body>>>) -> Self {
+        Self { slot, body: None }
+    }
+
+    /// Applies `f` to the body, wherever it is. A body taken back by the
+    /// runtime leaves this one empty.
+    fn with_body<T>(&self, f: impl FnOnce(&Body) -> T, empty: T) -> T {
+        if let Some(body) = &self.body {
+            return f(body);
+        }
+        match self.slot.lock() {
+            Ok(slot) => slot.as_ref().map_or(empty, f),
+            Err(_) => empty,
+        }
+    }
+}
+
+impl http_body::Body for Deferred {
+    type Data = Bytes;
+    type Error = BoxError;
+
+    fn poll_frame(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        if self.body.is_none() {
+            let body = match self.slot.lock() {
+                Ok(mut slot) => slot.take(),
+                Err(_) => return Poll::Ready(Some(Err("Body slot is poisoned".into()))),
+            };
+            let Some(body) = body else {
+                return Poll::Ready(None);
+            };
+            self.body = Some(body);
+        }
+        match self.body.as_mut() {
+            Some(body) => http_body::Body::poll_frame(Pin::new(body), cx).map_err(Into::into),
+            None => Poll::Ready(None),
+        }
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.with_body(http_body::Body::is_end_stream, true)
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.with_body(http_body::Body::size_hint, SizeHint::with_exact(0))
+    }
+}
@@ -0,0 +1,29 @@
+use std::str::FromStr;
+
+use super::{Context, message};
+use crate::bindings::v0_1::{Host, Request};
+
+impl Host for Context {}
+
+impl Request for Context {
+    async fn headers(&mut self) -> Vec<(String, String)> {
+        message::headers(&self.request)
+            .into_iter()
+            .map(|(name, value)| (name, String::from_utf8_lossy(&value).into_owned()))
+            .collect()
+    }
+
+    async fn set_header(&mut self, key: String, value: String) -> Result<(), String> {
+        message::set_header(&mut self.request, key, value.into_bytes())
+    }
+
+    async fn uri(&mut self) -> String {
+        self.request.uri().to_string()
+    }
+
+    async fn set_uri(&mut self, uri: String) -> Result<(), String> {
+        http::Uri::from_str(&uri)
+            .map(|u| *self.request.uri_mut() = u)
+            .map_err(|e| format!("Could not create uri {}: {}", uri, e))
+    }
+}
@@ -0,0 +1,37 @@
+use std::fmt;
+use wasmtime::Trap;
+
+use crate::limiter::LimitExceeded;
+
+#[derive(Debug)]
+pub enum Error {
+    /// The component exceeded its execution time limit.
+    Timeout,
+    /// The component grew its memory or tables beyond the configured limits.
+    MemoryLimit(String),
+    Failed(anyhow::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Timeout => write!(f, "Component exceeded its execution time limit"),
+            Error::MemoryLimit(error) => write!(f, "{}", error),
+            Error::Failed(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<anyhow::Error> for Error {
+    fn from(error: anyhow::Error) -> Self {
+        if let Some(limit_exceeded) = error.downcast_ref::<LimitExceeded>() {
+            return Error::MemoryLimit(limit_exceeded.to_string());
+        }
+        match error.downcast_ref::<Trap>() {
+            Some(Trap::Interrupt) => Error::Timeout,
+            _ => Error::Failed(error),
+        }
+    }
+}
@@ -0,0 +1,40 @@
+use anyhow::Result;
+use std::sync::Arc;
+
+/// Persistence behind the `kv` interface.
+#[async_trait::async_trait]
+pub trait KeyValueStore: Send + Sync {
+    async fn get(&self, namespace: &str, key: &str) -> Result<Option<Vec<u8>>>;
+    async fn set(&self, namespace: &str, key: &str, value: Vec<u8>) -> Result<()>;
+    async fn delete(&self, namespace: &str, key: &str) -> Result<()>;
+    async fn increment(&self, namespace: &str, key: &str, delta: i64) -> Result<i64>;
+}
+
+/// The part of the store a single proxy is allowed to see.
+#[derive(Clone)]
+pub struct Namespace {
+    store: Arc<dyn KeyValueStore>,
+    namespace: String,
+}
+
+impl Namespace {
+    pub fn new(store: Arc<dyn KeyValueStore>, namespace: String) -> Self {
+        Self { store, namespace }
+    }
+
+    pub async fn get(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        self.store.get(&self.namespace, key).await
+    }
+
+    pub async fn set(&self, key: &str, value: Vec<u8>) -> Result<()> {
+        self.store.set(&self.namespace, key, value).await
+    }
+
+    pub async fn delete(&self, key: &str) -> Result<()> {
+        self.store.delete(&self.namespace, key).await
+    }
+
+    pub async fn increment(&self, key: &str, delta: i64) -> Result<i64> {
+        self.store.increment(&self.namespace, key, delta).await
+    }
+}
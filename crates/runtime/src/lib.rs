@@ -1,97 +1,575 @@
 mod bindings;
+mod cache;
+mod capabilities;
+pub mod client;
+mod compiled;
+pub mod configuration;
 mod context;
+mod error;
+pub mod kv;
+mod limiter;
+mod logging;
+mod prepared;
 pub mod proxy;
 pub mod resolution;
+pub mod rollback;
+mod stats;
+pub mod validation;
 
 use anyhow::{Result, anyhow};
 use rama::http::{Body, Request as RamaRequest, Response as RamaResponse};
 use wasmtime::component::{Component, Linker, TypedFunc};
-use wasmtime::{Engine, Store};
+use wasmtime::{
+    Config, Engine, InstanceAllocationStrategy, PoolingAllocationConfig, Store, Trap,
+    UpdateDeadline,
+};
 
-use resolution::Resolution;
+use cache::ArtifactCache;
+use capabilities::Capabilities;
+use client::Client;
+use compiled::Compiled;
+use configuration::{Configuration, fallback};
+pub use error::Error;
+use kv::{KeyValueStore, Namespace};
+use limiter::Limiter;
+use logging::Logger;
+use prepared::PreparedComponent;
+use proxy::Proxy;
+use resolution::{Forward, Resolution, ResponseHook, Target};
+use rollback::{Monitor, Rollback};
+pub use stats::Snapshot as Stats;
+use validation::Incompatible;
 
 pub type Request = ();
-pub type ProxyFunc = TypedFunc<(), (bindings::Resolution,)>;
+pub type ResponseHookFunc = TypedFunc<(), (Option<bindings::Response>,)>;
 
-use std::sync::{Arc, RwLock};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+
+/// Interval in which the engine's epoch advances, guests yield to the async
+/// runtime and check their execution deadline at every tick.
+const EPOCH_TICK: Duration = Duration::from_millis(10);
 
 #[derive(Clone)]
 pub struct Runtime {
     engine: Engine,
-    linker: Linker<context::Context>,
-    component: Arc<RwLock<Component>>,
+    linker: Arc<Linker<context::Context>>,
+    configuration: Arc<Configuration>,
+    store: Arc<dyn KeyValueStore>,
+    cache: Option<Arc<ArtifactCache>>,
+    compiled: Arc<Compiled>,
+    default_proxy: ActiveProxy,
+    chain: Arc<RwLock<Arc<[ActiveProxy]>>>,
+    fallback: Arc<RwLock<Option<ActiveProxy>>>,
+    stats: Arc<stats::Stats>,
+    monitor: Arc<Monitor>,
+}
+
+/// A component serving requests, `tag` is `None` for the built-in default.
+#[derive(Clone)]
+struct ActiveProxy {
+    tag: Option<String>,
+    component: PreparedComponent,
+    configuration: Arc<HashMap<String, String>>,
+    capabilities: Arc<Capabilities>,
 }
 
 impl Runtime {
-    pub fn new(default_proxy: &[u8]) -> Result<Self> {
-        let engine = wasmtime::Engine::default();
+    pub fn new(
+        configuration: &Configuration,
+        store: Arc<dyn KeyValueStore>,
+        default_proxy: &[u8],
+    ) -> Result<Self> {
+        let mut config = Config::new();
+        config.async_support(true);
+        config.epoch_interruption(true);
+        if let Some(pooling) = &configuration.pooling {
+            let mut pooling_config = PoolingAllocationConfig::default();
+            pooling_config
+                .total_component_instances(pooling.total_component_instances)
+                .total_core_instances(pooling.total_core_instances)
+                .total_memories(pooling.total_memories)
+                .total_tables(pooling.total_tables)
+                .max_memory_size(pooling.max_memory_size);
+            config.allocation_strategy(InstanceAllocationStrategy::Pooling(pooling_config));
+        }
+        let engine = Engine::new(&config)?;
+        spawn_epoch_ticker(&engine);
         let mut linker = Linker::new(&engine);
-        wasmtime_wasi::p2::add_to_linker_sync(&mut linker)?;
+        wasmtime_wasi::p2::add_to_linker_async(&mut linker)?;
         bindings::add_to_linker(&mut linker)?;
-        let component = Component::from_binary(&engine, default_proxy)?;
+        let cache = configuration
+            .cache
+            .as_ref()
+            .map(|cache| ArtifactCache::new(&engine, cache).map(Arc::new))
+            .transpose()?;
+        let component = compile(&engine, cache.as_deref(), default_proxy)?;
+        let component = PreparedComponent::new(&linker, &component)?;
+        let default_proxy = ActiveProxy {
+            tag: None,
+            component,
+            configuration: Default::default(),
+            capabilities: Arc::new(Capabilities::resolve(configuration.capabilities(None))?),
+        };
         let runtime = Self {
             engine,
-            linker,
-            component: Arc::new(RwLock::new(component)),
+            linker: Arc::new(linker),
+            configuration: Arc::new(configuration.clone()),
+            store,
+            cache,
+            compiled: Default::default(),
+            chain: Arc::new(RwLock::new(Arc::new([default_proxy.clone()]))),
+            default_proxy,
+            fallback: Default::default(),
+            stats: Default::default(),
+            monitor: Arc::new(Monitor::new(configuration.rollback.clone())),
         };
         Ok(runtime)
     }
 
-    pub fn process(&self, request: RamaRequest) -> Result<Resolution> {
-        let mut store = Store::new(&self.engine, context::Context::new(request));
-        let component = self
-            .component
+    pub async fn process(&self, request: RamaRequest) -> Result<Resolution, Error> {
+        let request_id = request_id(&request);
+        let (request, replay) = self.replay(request);
+        let result = self.run(request, &request_id).await;
+        self.stats.record(&result);
+        match result {
+            Err(error) => {
+                let replay = replay.and_then(Replay::into_request);
+                self.fall_back(error, replay, &request_id).await
+            }
+            result => result,
+        }
+    }
+
+    pub fn stats(&self) -> Stats {
+        self.stats.snapshot()
+    }
+
+    /// Notifies about proxies that trapped too often and should be replaced.
+    pub fn subscribe_rollbacks(&self) -> tokio::sync::broadcast::Receiver<Rollback> {
+        self.monitor.subscribe()
+    }
+
+    pub fn fallback_tag(&self) -> Option<&str> {
+        self.configuration.fallback_tag()
+    }
+
+    /// Passes the request through the chain, each component sees the request
+    /// as modified by the ones before it. A component responding ends the
+    /// chain, otherwise the last component picking a target decides where the
+    /// request is forwarded to.
+    async fn run(&self, mut request: RamaRequest, request_id: &str) -> Result<Resolution, Error> {
+        let chain = self
+            .chain
             .read()
-            .map_err(|e| anyhow!("Failed to acquire read lock of component: {}", e))?;
-        let proxy_func = extract_proxy_function(&self.linker, &mut store, &component)?;
-        drop(component);
+            .map_err(|e| anyhow!("Failed to acquire read lock of chain: {}", e))?
+            .clone();
+        let mut target = None;
+        let mut hooks = Vec::new();
+        for proxy in chain.iter() {
+            let step = self
+                .step(proxy, request, request_id)
+                .await
+                .inspect_err(|error| {
+                    self.monitor.record(proxy.tag.as_deref(), error);
+                })?;
+            match step {
+                Step::Forward {
+                    request: forwarded,
+                    target: step_target,
+                    hook,
+                } => {
+                    request = forwarded;
+                    target = step_target.or(target);
+                    if hook {
+                        hooks.push(proxy.clone());
+                    }
+                }
+                Step::Respond(response) => return Ok(Resolution::Respond(response)),
+            }
+        }
+        let response_hook = self.response_hook(hooks, &request, request_id);
+        Ok(Resolution::Forward(Forward {
+            request,
+            target,
+            response_hook,
+        }))
+    }
 
-        let (result,) = proxy_func.call(&mut store, ())?;
-        proxy_func.post_return(&mut store)?;
+    /// Keeps what the fallback proxy needs to be served the request as it
+    /// came in. The body is only handed to the chain once a component reads
+    /// it, so nothing is buffered up front.
+    fn replay(&self, request: RamaRequest) -> (RamaRequest, Option<Replay>) {
+        let Some(fallback::Configuration::Proxy(_)) = &self.configuration.fallback else {
+            return (request, None);
+        };
+        let head = request_head(&request);
+        let (parts, body) = request.into_parts();
+        let body = Arc::new(Mutex::new(Some(body)));
+        let deferred = Body::new(context::message::Deferred::new(body.clone()));
+        (
+            RamaRequest::from_parts(parts, deferred),
+            Some(Replay { head, body }),
+        )
+    }
 
-        match result {
-            bindings::Resolution::Forward => Ok(Resolution::Forward(store.into_data().request)),
-            bindings::Resolution::Respond(bindings::Response { status_code, body }) => {
-                let body = body.map(|bytes| Body::from(bytes)).unwrap_or(Body::empty());
-                let response = RamaResponse::builder().status(status_code).body(body)?;
-                Ok(Resolution::Respond(response))
+    /// Serves the configured fallback in place of a chain that failed, without
+    /// one the error is passed on.
+    async fn fall_back(
+        &self,
+        error: Error,
+        replay: Option<RamaRequest>,
+        request_id: &str,
+    ) -> Result<Resolution, Error> {
+        match &self.configuration.fallback {
+            Some(fallback::Configuration::Response(response)) => {
+                tracing::warn!(request_id, %error, "Serving fallback response");
+                Ok(Resolution::Respond(fallback_response(response)?))
+            }
+            Some(fallback::Configuration::Proxy(_)) => {
+                let proxy = self
+                    .fallback
+                    .read()
+                    .map_err(|e| anyhow!("Failed to acquire read lock of fallback: {}", e))?
+                    .clone();
+                let (Some(proxy), Some(request)) = (proxy, replay) else {
+                    return Err(error);
+                };
+                tracing::warn!(request_id, %error, "Serving fallback proxy");
+                let step = self
+                    .step(&proxy, request, request_id)
+                    .await
+                    .inspect_err(|error| {
+                        self.stats.record_error(error);
+                        self.monitor.record(proxy.tag.as_deref(), error);
+                    })?;
+                match step {
+                    Step::Forward {
+                        request,
+                        target,
+                        hook,
+                    } => {
+                        let proxies = hook.then_some(proxy).into_iter().collect();
+                        let response_hook = self.response_hook(proxies, &request, request_id);
+                        Ok(Resolution::Forward(Forward {
+                            request,
+                            target,
+                            response_hook,
+                        }))
+                    }
+                    Step::Respond(response) => Ok(Resolution::Respond(response)),
+                }
+            }
+            None => Err(error),
+        }
+    }
+
+    fn response_hook(
+        &self,
+        proxies: Vec<ActiveProxy>,
+        request: &RamaRequest,
+        request_id: &str,
+    ) -> Option<Box<ResponseHook>> {
+        (!proxies.is_empty()).then(|| {
+            Box::new(ResponseHook {
+                runtime: self.clone(),
+                proxies,
+                request: request_head(request),
+                request_id: request_id.to_string(),
+            })
+        })
+    }
+
+    async fn step(
+        &self,
+        proxy: &ActiveProxy,
+        request: RamaRequest,
+        request_id: &str,
+    ) -> Result<Step, Error> {
+        let mut store = self.new_store(proxy, request, request_id)?;
+        let (proxy_func, _) = proxy.component.instantiate(&mut store).await?;
+
+        let result = proxy_func.call(&mut store).await?;
+
+        let target = match result {
+            bindings::Resolution::Forward => None,
+            bindings::Resolution::ForwardTo(bindings::Target::Address(address)) => {
+                Some(Target::Address(address))
+            }
+            bindings::Resolution::ForwardTo(bindings::Target::Upstream(name)) => {
+                Some(Target::Upstream(name))
             }
+            bindings::Resolution::Respond(response) => {
+                return Ok(Step::Respond(into_rama_response(response)?));
+            }
+        };
+        // The instance is released here, a response hook gets a new one once
+        // the upstream answered.
+        let request = std::mem::take(&mut store.data_mut().request);
+        let hook = proxy.component.has_response_hook();
+        Ok(Step::Forward {
+            request,
+            target,
+            hook,
+        })
+    }
+
+    /// Runs the `on-response` export of `proxy` in a new instance, `request`
+    /// is the head of the request the upstream answered.
+    async fn on_response(
+        &self,
+        proxy: &ActiveProxy,
+        request: &RamaRequest,
+        response: RamaResponse,
+        request_id: &str,
+    ) -> Result<RamaResponse, Error> {
+        let mut store = self.new_store(proxy, request_head(request), request_id)?;
+        store.data_mut().response = response;
+        let func = match proxy.component.instantiate(&mut store).await? {
+            (_, Some(func)) => func,
+            (_, None) => return Err(anyhow!("Component exports no response hook").into()),
+        };
+        let (result,) = func.call_async(&mut store, ()).await?;
+        func.post_return_async(&mut store).await?;
+        match result {
+            Some(response) => Ok(into_rama_response(response)?),
+            None => Ok(store.into_data().response),
+        }
+    }
+
+    /// Store for a single invocation of `proxy`, with the limits of its tag.
+    fn new_store(
+        &self,
+        proxy: &ActiveProxy,
+        request: RamaRequest,
+        request_id: &str,
+    ) -> Result<Store<context::Context>> {
+        let tag = proxy.tag.as_deref();
+        let client = Client::new(self.configuration.client(tag).clone());
+        let kv = Namespace::new(self.store.clone(), tag.unwrap_or_default().to_string());
+        let configuration = proxy.configuration.clone();
+        let logger = Logger::new(
+            tag.unwrap_or("default").to_string(),
+            request_id.to_string(),
+            self.configuration.log_level(tag),
+        );
+        let limits = self.configuration.limits(tag);
+        let timeout = Duration::from_millis(limits.execution_timeout_ms);
+        let limiter = Limiter::new(limits);
+        let context = context::Context::new(
+            request,
+            client,
+            kv,
+            configuration,
+            logger,
+            limiter,
+            &proxy.capabilities,
+        )?;
+        let mut store = Store::new(&self.engine, context);
+        store.limiter(|context| &mut context.limiter);
+        set_deadline(&mut store, timeout);
+        Ok(store)
+    }
+
+    /// Compiles the component and type-checks it against the crossroads
+    /// world, without instantiating it. A valid component is kept in memory
+    /// for loading it, the artifact is only written once it is loaded.
+    pub fn validate(&self, bytes: &[u8]) -> Result<(), Incompatible> {
+        let component = Component::from_binary(&self.engine, bytes).map_err(Incompatible::error)?;
+        let incompatible = Incompatible {
+            missing_exports: PreparedComponent::missing_exports(&self.engine, &component),
+            unsatisfied_imports: validation::unsatisfied_imports(&self.linker, &component),
+            errors: Vec::new(),
+        };
+        if !incompatible.is_empty() {
+            return Err(incompatible);
         }
+        PreparedComponent::new(&self.linker, &component).map_err(Incompatible::error)?;
+        self.compiled.insert(bytes, component);
+        Ok(())
+    }
+
+    /// Serves requests with a single component.
+    pub fn set_proxy(&self, proxy: &Proxy) -> Result<()> {
+        self.set_chain(std::slice::from_ref(proxy))
+    }
+
+    /// Serves requests with the components in order, an empty chain falls back
+    /// to the built-in default component. Components not compiled while being
+    /// validated are compiled here, which blocks the calling thread.
+    pub fn set_chain(&self, proxies: &[Proxy]) -> Result<()> {
+        let chain = proxies
+            .iter()
+            .map(|proxy| self.load(proxy))
+            .collect::<Result<Vec<_>>>()?;
+        let chain: Arc<[ActiveProxy]> = if chain.is_empty() {
+            Arc::new([self.default_proxy.clone()])
+        } else {
+            chain.into()
+        };
+        let mut lock = self
+            .chain
+            .write()
+            .map_err(|e| anyhow!("Failed to acquire write lock to update chain: {}", e))?;
+        *lock = chain;
+        Ok(())
     }
 
-    pub fn set_proxy(&self, component: &[u8]) -> Result<()> {
-        let component = Component::from_binary(&self.engine, component)?;
+    /// Loads the proxy served in place of one that trapped.
+    pub fn set_fallback(&self, proxy: Option<&Proxy>) -> Result<()> {
+        let fallback = proxy.map(|proxy| self.load(proxy)).transpose()?;
         let mut lock = self
-            .component
+            .fallback
             .write()
-            .map_err(|e| anyhow!("Failed to acquire write lock to update component: {}", e))?;
-        *lock = component;
+            .map_err(|e| anyhow!("Failed to acquire write lock to update fallback: {}", e))?;
+        *lock = fallback;
         Ok(())
     }
+
+    /// Drops the cached artifact of a component that was deleted or replaced,
+    /// the cache would otherwise keep it forever.
+    pub fn forget(&self, component: &[u8]) {
+        self.compiled.take(component);
+        let Some(cache) = &self.cache else {
+            return;
+        };
+        if let Err(error) = cache.remove(component) {
+            tracing::warn!(%error, "Failed to remove cached component");
+        }
+    }
+
+    /// Drops the traps counted for a deleted proxy along with the cached
+    /// artifact of its component.
+    pub fn forget_proxy(&self, proxy: &Proxy) {
+        self.monitor.forget(&proxy.metadata.tag);
+        self.forget(&proxy.component);
+    }
+
+    /// Compiles the proxy unless it was compiled while being validated.
+    fn load(&self, proxy: &Proxy) -> Result<ActiveProxy> {
+        let component = match self.compiled.take(&proxy.component) {
+            Some(component) => {
+                if let Some(cache) = &self.cache {
+                    cache.insert(&proxy.component, &component);
+                }
+                component
+            }
+            None => compile(&self.engine, self.cache.as_deref(), &proxy.component)?,
+        };
+        Ok(ActiveProxy {
+            tag: Some(proxy.metadata.tag.clone()),
+            component: PreparedComponent::new(&self.linker, &component)?,
+            configuration: Arc::new(proxy.configuration_entries()),
+            capabilities: Arc::new(Capabilities::resolve(
+                self.configuration.capabilities(Some(&proxy.metadata.tag)),
+            )?),
+        })
+    }
+}
+
+/// Request as it came in, for the fallback proxy.
+struct Replay {
+    head: RamaRequest,
+    /// Taken by the chain once a component reads the body.
+    body: Arc<Mutex<Option<Body>>>,
+}
+
+impl Replay {
+    /// The request, unless the chain consumed its body.
+    fn into_request(self) -> Option<RamaRequest> {
+        let body = self.body.lock().ok()?.take()?;
+        let (parts, _) = self.head.into_parts();
+        Some(RamaRequest::from_parts(parts, body))
+    }
+}
+
+/// Outcome of a single component of the chain.
+enum Step {
+    Forward {
+        request: RamaRequest,
+        target: Option<Target>,
+        /// Whether the proxy exports `on-response`.
+        hook: bool,
+    },
+    Respond(RamaResponse),
+}
+
+fn compile(engine: &Engine, cache: Option<&ArtifactCache>, bytes: &[u8]) -> Result<Component> {
+    match cache {
+        Some(cache) => cache.load(engine, bytes),
+        None => Component::from_binary(engine, bytes),
+    }
+}
+
+/// Lets the guest execute for `timeout`, yielding at every epoch tick. The
+/// time is counted in ticks observed while guest code runs, so host calls and
+/// waiting to be resumed after a yield cost at most a single tick.
+fn set_deadline(store: &mut Store<context::Context>, timeout: Duration) {
+    let ticks = timeout.as_millis().div_ceil(EPOCH_TICK.as_millis()).max(1);
+    let mut remaining = u64::try_from(ticks).unwrap_or(u64::MAX);
+    store.epoch_deadline_callback(move |_| {
+        remaining -= 1;
+        if remaining == 0 {
+            return Err(Trap::Interrupt.into());
+        }
+        Ok(UpdateDeadline::Yield(1))
+    });
+    store.set_epoch_deadline(1);
+}
+
+/// Advances the epoch until the engine is dropped.
+fn spawn_epoch_ticker(engine: &Engine) {
+    let engine = engine.weak();
+    std::thread::spawn(move || {
+        while let Some(engine) = engine.upgrade() {
+            engine.increment_epoch();
+            drop(engine);
+            std::thread::sleep(EPOCH_TICK);
+        }
+    });
+}
+
+/// Reuses the `x-request-id` of the client or generates one to correlate the
+/// guest log events of the request. A generated ID is not added to the
+/// request, so it does not reach the upstream.
+fn request_id(request: &RamaRequest) -> String {
+    request
+        .headers()
+        .get("x-request-id")
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string)
+        .unwrap_or_else(|| uuid::Uuid::new_v4().to_string())
+}
+
+/// Method, URI, version and headers of `request`, without its body.
+fn request_head(request: &RamaRequest) -> RamaRequest {
+    let mut head = RamaRequest::new(Body::empty());
+    *head.method_mut() = request.method().clone();
+    *head.uri_mut() = request.uri().clone();
+    *head.version_mut() = request.version();
+    *head.headers_mut() = request.headers().clone();
+    head
+}
+
+fn fallback_response(response: &fallback::Response) -> Result<RamaResponse> {
+    let response = RamaResponse::builder()
+        .status(response.status_code)
+        .body(Body::from(response.body.clone()))?;
+    Ok(response)
 }
 
-fn extract_proxy_function(
-    linker: &Linker<context::Context>,
-    store: &mut Store<context::Context>,
-    component: &Component,
-) -> Result<ProxyFunc> {
-    let instance = linker
-        .instantiate(&mut *store, component)
-        .map_err(|e| anyhow!("Failed to instantiate component: {}", e))?;
-
-    let interface_namespace = "wit:crossroads/proxy@0.1.0";
-    let interface_idx = instance
-        .get_export_index(&mut *store, None, interface_namespace)
-        .expect(&format!("Cannot get `{}` interface", interface_namespace));
-
-    let parent_export_idx = Some(&interface_idx);
-    let func_id_handle_request = instance
-        .get_export_index(&mut *store, parent_export_idx, "handle")
-        .expect(&format!("Cannot get `{}` function", "handle"));
-
-    let func_handle_request = instance
-        .get_func(&mut *store, func_id_handle_request)
-        .expect("Unreachable since we've got func_idx");
-
-    func_handle_request.typed::<(), (bindings::Resolution,)>(store)
+fn into_rama_response(response: bindings::Response) -> Result<RamaResponse> {
+    let bindings::Response {
+        status_code,
+        headers,
+        body,
+    } = response;
+    let body = body.map(Body::from).unwrap_or(Body::empty());
+    let builder = RamaResponse::builder().status(status_code);
+    let builder = headers
+        .into_iter()
+        .fold(builder, |builder, (key, value)| builder.header(key, value));
+    let response = builder.body(body)?;
+    Ok(response)
 }
@@ -0,0 +1,74 @@
+use anyhow::Result;
+use std::fmt;
+use wasmtime::ResourceLimiter;
+
+use crate::configuration::limits::Configuration;
+
+/// Caps linear memory and tables of a single component instance, and the
+/// bodies buffered for it on the host.
+pub(crate) struct Limiter {
+    max_memory_bytes: usize,
+    max_table_elements: usize,
+    max_body_bytes: u64,
+}
+
+/// Trap raised when a guest grows beyond its limits.
+#[derive(Debug)]
+pub(crate) struct LimitExceeded(String);
+
+impl fmt::Display for LimitExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for LimitExceeded {}
+
+impl Limiter {
+    pub(crate) fn new(configuration: &Configuration) -> Self {
+        Self {
+            max_memory_bytes: configuration.max_memory_bytes,
+            max_table_elements: configuration.max_table_elements,
+            max_body_bytes: configuration.max_body_bytes,
+        }
+    }
+
+    /// Limit of a body buffered on the host, at most the configured one.
+    pub(crate) fn body_limit(&self, requested: u64) -> u64 {
+        requested.min(self.max_body_bytes)
+    }
+}
+
+impl ResourceLimiter for Limiter {
+    fn memory_growing(
+        &mut self,
+        _current: usize,
+        desired: usize,
+        _maximum: Option<usize>,
+    ) -> Result<bool> {
+        if desired > self.max_memory_bytes {
+            return Err(LimitExceeded(format!(
+                "Memory limit of {} bytes exceeded, {} bytes requested",
+                self.max_memory_bytes, desired
+            ))
+            .into());
+        }
+        Ok(true)
+    }
+
+    fn table_growing(
+        &mut self,
+        _current: usize,
+        desired: usize,
+        _maximum: Option<usize>,
+    ) -> Result<bool> {
+        if desired > self.max_table_elements {
+            return Err(LimitExceeded(format!(
+                "Table limit of {} elements exceeded, {} elements requested",
+                self.max_table_elements, desired
+            ))
+            .into());
+        }
+        Ok(true)
+    }
+}
@@ -0,0 +1,202 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Mutex, OnceLock, PoisonError};
+use tracing::level_filters::LevelFilter;
+use tracing_core::callsite::{self, Callsite};
+use tracing_core::field::{Field, FieldSet, Value};
+use tracing_core::metadata::Kind;
+use tracing_core::subscriber::Interest;
+use tracing_core::{Event, Metadata, dispatcher, identify_callsite};
+
+use crate::configuration::logging::Level;
+
+/// Fields every guest event starts with, the fields of the guest follow.
+const FIXED_FIELDS: [&str; 3] = ["message", "tag", "request_id"];
+/// Prefix of guest field names, keeps them apart from the fixed fields.
+const FIELD_PREFIX: &str = "fields.";
+/// Guest fields recorded per event, the ones beyond are dropped.
+const MAX_FIELDS: usize = 32;
+/// Callsites a single proxy may create for its sets of field names. Events
+/// with further sets record their fields together as one `fields` value.
+const MAX_CALLSITES_PER_PROXY: usize = 64;
+/// Callsites created for all proxies together. Their field names are leaked,
+/// so this bounds the memory guest events can hold on to.
+const MAX_CALLSITES: usize = 4096;
+
+/// Turns guest log calls into `tracing` events tagged with the proxy tag and
+/// the request they were emitted for. Guest fields are recorded as fields of
+/// the event prefixed with `fields.`, which needs a callsite per level and
+/// set of field names.
+pub(crate) struct Logger {
+    tag: String,
+    request_id: String,
+    max_level: Level,
+}
+
+impl Logger {
+    pub(crate) fn new(tag: String, request_id: String, max_level: Level) -> Self {
+        Self {
+            tag,
+            request_id,
+            max_level,
+        }
+    }
+
+    pub(crate) fn log(&self, level: Level, message: &str, fields: &[(String, String)]) {
+        if level > self.max_level {
+            return;
+        }
+        let level = match level {
+            Level::Off => return,
+            Level::Error => tracing::Level::ERROR,
+            Level::Warn => tracing::Level::WARN,
+            Level::Info => tracing::Level::INFO,
+            Level::Debug => tracing::Level::DEBUG,
+            Level::Trace => tracing::Level::TRACE,
+        };
+        if level > LevelFilter::current() {
+            return;
+        }
+        let fields = &fields[..fields.len().min(MAX_FIELDS)];
+        let names = fields
+            .iter()
+            .map(|(name, _)| format!("{}{}", FIELD_PREFIX, name))
+            .collect();
+        let fixed: [&dyn Value; 3] = [&message, &self.tag, &self.request_id];
+        match Registry::callsite(&self.tag, level, names) {
+            Some(callsite) => {
+                let values = fields.iter().map(|(_, value)| value as &dyn Value);
+                callsite.dispatch(fixed.into_iter().chain(values).collect());
+            }
+            None => {
+                let fields = tracing::field::debug(GuestFields(fields));
+                Registry::fallback(level)
+                    .dispatch(fixed.into_iter().chain([&fields as &dyn Value]).collect());
+            }
+        }
+    }
+}
+
+/// Key-value pairs of a guest event, formatted as a map.
+struct GuestFields<'a>(&'a [(String, String)]);
+
+impl fmt::Debug for GuestFields<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map()
+            .entries(self.0.iter().map(|(key, value)| (key, value)))
+            .finish()
+    }
+}
+
+static REGISTRY: OnceLock<Mutex<Registry>> = OnceLock::new();
+
+/// Callsites of guest events by level and field names, along with the number
+/// each proxy created.
+#[derive(Default)]
+struct Registry {
+    callsites: HashMap<(tracing::Level, Vec<String>), &'static GuestCallsite>,
+    created: HashMap<String, usize>,
+    fallbacks: HashMap<tracing::Level, &'static GuestCallsite>,
+}
+
+impl Registry {
+    /// Callsite for `names`, none if it is new and the proxy or all proxies
+    /// together created as many as they may.
+    fn callsite(
+        tag: &str,
+        level: tracing::Level,
+        names: Vec<String>,
+    ) -> Option<&'static GuestCallsite> {
+        let registry = REGISTRY.get_or_init(Default::default);
+        let mut registry = registry.lock().unwrap_or_else(PoisonError::into_inner);
+        let key = (level, names);
+        if let Some(callsite) = registry.callsites.get(&key) {
+            return Some(*callsite);
+        }
+        let created = registry.created.get(tag).copied().unwrap_or_default();
+        if created >= MAX_CALLSITES_PER_PROXY || registry.callsites.len() >= MAX_CALLSITES {
+            return None;
+        }
+        let callsite = GuestCallsite::create(level, &key.1);
+        registry.callsites.insert(key, callsite);
+        registry.created.insert(tag.to_string(), created + 1);
+        Some(callsite)
+    }
+
+    /// Callsite recording all guest fields as one `fields` value, one per
+    /// level and shared by every proxy.
+    fn fallback(level: tracing::Level) -> &'static GuestCallsite {
+        let registry = REGISTRY.get_or_init(Default::default);
+        let mut registry = registry.lock().unwrap_or_else(PoisonError::into_inner);
+        *registry
+            .fallbacks
+            .entry(level)
+            .or_insert_with(|| GuestCallsite::create(level, &["fields".to_string()]))
+    }
+}
+
+/// Callsite of guest events at one level with one set of field names.
+struct GuestCallsite {
+    metadata: OnceLock<Metadata<'static>>,
+}
+
+impl GuestCallsite {
+    fn create(level: tracing::Level, names: &[String]) -> &'static Self {
+        let names = FIXED_FIELDS
+            .into_iter()
+            .chain(names.iter().map(|name| {
+                let name: &'static str = Box::leak(name.clone().into_boxed_str());
+                name
+            }))
+            .collect::<Vec<_>>();
+        let callsite: &'static Self = Box::leak(Box::new(GuestCallsite {
+            metadata: OnceLock::new(),
+        }));
+        let metadata = Metadata::new(
+            "guest event",
+            "guest",
+            level,
+            Some(file!()),
+            Some(line!()),
+            Some(module_path!()),
+            FieldSet::new(
+                Box::leak(names.into_boxed_slice()),
+                identify_callsite!(callsite),
+            ),
+            Kind::EVENT,
+        );
+        let _ = callsite.metadata.set(metadata);
+        callsite::register(callsite);
+        callsite
+    }
+
+    /// Emits an event with `values` in the order of the fields of the callsite.
+    fn dispatch(&'static self, values: Vec<&dyn Value>) {
+        let Some(metadata) = self.metadata.get() else {
+            return;
+        };
+        let fields = metadata.fields().iter().collect::<Vec<_>>();
+        // Value sets take arrays, unused entries repeat a field without a value.
+        let entries: [(&Field, Option<&dyn Value>); FIXED_FIELDS.len() + MAX_FIELDS] =
+            std::array::from_fn(|i| match fields.get(i) {
+                Some(field) => (field, values.get(i).copied()),
+                None => (&fields[0], None),
+            });
+        dispatcher::get_default(|dispatch| {
+            if dispatch.enabled(metadata) {
+                dispatch.event(&Event::new(
+                    metadata,
+                    &metadata.fields().value_set(&entries),
+                ));
+            }
+        });
+    }
+}
+
+impl Callsite for GuestCallsite {
+    fn set_interest(&self, _: Interest) {}
+
+    fn metadata(&self) -> &Metadata<'_> {
+        self.metadata.get().expect("Metadata is set on creation")
+    }
+}
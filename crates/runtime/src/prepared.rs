@@ -0,0 +1,173 @@
+use anyhow::{Context as _, Result, anyhow};
+use wasmtime::component::{Component, ComponentExportIndex, InstancePre, Linker, TypedFunc};
+use wasmtime::{Engine, Store};
+
+use crate::context::Context;
+use crate::{ResponseHookFunc, bindings};
+
+const PROXY_INTERFACE: &str = "wit:crossroads/proxy";
+const RESPONSE_HOOK_INTERFACE: &str = "wit:crossroads/response-hook";
+
+/// Versions of the crossroads world the host provides bindings for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum WorldVersion {
+    V0_1,
+    V0_2,
+}
+
+impl WorldVersion {
+    pub(crate) const LATEST: WorldVersion = WorldVersion::V0_2;
+
+    /// Matches every patch release of a supported minor version.
+    pub(crate) fn parse(version: &str) -> Option<Self> {
+        let mut parts = version.split('.');
+        match (parts.next(), parts.next()) {
+            (Some("0"), Some("1")) => Some(WorldVersion::V0_1),
+            (Some("0"), Some("2")) => Some(WorldVersion::V0_2),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn proxy_interface(self) -> String {
+        format!("{}@{}", PROXY_INTERFACE, self)
+    }
+
+    /// Detects the version from the `proxy` interface the component exports.
+    pub(crate) fn detect(engine: &Engine, component: &Component) -> Option<(Self, String)> {
+        component
+            .component_type()
+            .exports(engine)
+            .filter_map(|(name, _)| {
+                let (interface, version) = name.split_once('@')?;
+                (interface == PROXY_INTERFACE)
+                    .then(|| Self::parse(version))
+                    .flatten()
+                    .map(|version| (version, name.to_string()))
+            })
+            .max_by_key(|(version, _)| *version)
+    }
+}
+
+impl std::fmt::Display for WorldVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WorldVersion::V0_1 => write!(f, "0.1.0"),
+            WorldVersion::V0_2 => write!(f, "0.2.0"),
+        }
+    }
+}
+
+/// The `handle` export in the shape of the component's world version.
+pub(crate) enum ProxyFunc {
+    V0_1(TypedFunc<(), (bindings::v0_1::Resolution,)>),
+    V0_2(TypedFunc<(), (bindings::Resolution,)>),
+}
+
+impl ProxyFunc {
+    /// Calls `handle`, resolutions of older worlds are converted to the
+    /// latest one.
+    pub(crate) async fn call(&self, store: &mut Store<Context>) -> Result<bindings::Resolution> {
+        match self {
+            ProxyFunc::V0_1(func) => {
+                let (resolution,) = func.call_async(&mut *store, ()).await?;
+                func.post_return_async(&mut *store).await?;
+                Ok(resolution.into())
+            }
+            ProxyFunc::V0_2(func) => {
+                let (resolution,) = func.call_async(&mut *store, ()).await?;
+                func.post_return_async(&mut *store).await?;
+                Ok(resolution)
+            }
+        }
+    }
+}
+
+/// A component linked ahead of time with its exports resolved, so serving a
+/// request only has to instantiate it.
+#[derive(Clone)]
+pub(crate) struct PreparedComponent {
+    instance_pre: InstancePre<Context>,
+    version: WorldVersion,
+    handle: ComponentExportIndex,
+    on_response: Option<ComponentExportIndex>,
+}
+
+impl PreparedComponent {
+    pub(crate) fn new(linker: &Linker<Context>, component: &Component) -> Result<Self> {
+        let instance_pre = linker
+            .instantiate_pre(component)
+            .context("Failed to link component")?;
+
+        let (version, interface) = WorldVersion::detect(linker.engine(), component)
+            .ok_or_else(|| anyhow!("Cannot get a supported `{}` interface", PROXY_INTERFACE))?;
+        let interface_idx = component
+            .get_export_index(None, &interface)
+            .ok_or_else(|| anyhow!("Cannot get `{}` interface", interface))?;
+        let handle = component
+            .get_export_index(Some(&interface_idx), "handle")
+            .ok_or_else(|| anyhow!("Cannot get `{}` function", "handle"))?;
+
+        // Response hooks were introduced with 0.2.
+        let on_response = match version {
+            WorldVersion::V0_1 => None,
+            WorldVersion::V0_2 => component
+                .get_export_index(None, &format!("{}@{}", RESPONSE_HOOK_INTERFACE, version))
+                .and_then(|interface_idx| {
+                    component.get_export_index(Some(&interface_idx), "on-response")
+                }),
+        };
+
+        Ok(Self {
+            instance_pre,
+            version,
+            handle,
+            on_response,
+        })
+    }
+
+    pub(crate) fn has_response_hook(&self) -> bool {
+        self.on_response.is_some()
+    }
+
+    /// Exports required to serve requests the component lacks.
+    pub(crate) fn missing_exports(engine: &Engine, component: &Component) -> Vec<String> {
+        let Some((_, interface)) = WorldVersion::detect(engine, component) else {
+            return vec![WorldVersion::LATEST.proxy_interface()];
+        };
+        let handle = component
+            .get_export_index(None, &interface)
+            .and_then(|interface_idx| component.get_export_index(Some(&interface_idx), "handle"));
+        match handle {
+            Some(_) => Vec::new(),
+            None => vec![format!("{}#handle", interface)],
+        }
+    }
+
+    pub(crate) async fn instantiate(
+        &self,
+        store: &mut Store<Context>,
+    ) -> Result<(ProxyFunc, Option<ResponseHookFunc>)> {
+        let instance = self
+            .instance_pre
+            .instantiate_async(&mut *store)
+            .await
+            .context("Failed to instantiate component")?;
+        let handle = match self.version {
+            WorldVersion::V0_1 => {
+                ProxyFunc::V0_1(instance.get_typed_func(&mut *store, self.handle)?)
+            }
+            WorldVersion::V0_2 => {
+                ProxyFunc::V0_2(instance.get_typed_func(&mut *store, self.handle)?)
+            }
+        };
+        let on_response = self
+            .on_response
+            .as_ref()
+            .map(|on_response| {
+                instance
+                    .get_typed_func::<(), (Option<bindings::Response>,)>(&mut *store, on_response)
+            })
+            .transpose()?;
+        Ok((handle, on_response))
+    }
+}
@@ -1,11 +1,15 @@
 mod metadata;
 
+use std::collections::HashMap;
+
 pub use metadata::ProxyMetadata;
 
 #[derive(Debug)]
 pub struct Proxy {
     pub metadata: ProxyMetadata,
     pub component: Vec<u8>,
+    /// Document handed to the component through the `config` interface.
+    pub configuration: Option<serde_json::Value>,
 }
 
 impl Proxy {
@@ -13,6 +17,22 @@ impl Proxy {
         Self {
             metadata: ProxyMetadata::new(tag),
             component,
+            configuration: None,
         }
     }
+
+    /// Top-level keys of the configuration document, non-string values are
+    /// kept as JSON.
+    pub fn configuration_entries(&self) -> HashMap<String, String> {
+        let Some(serde_json::Value::Object(document)) = &self.configuration else {
+            return HashMap::new();
+        };
+        document
+            .iter()
+            .map(|(key, value)| match value {
+                serde_json::Value::String(value) => (key.clone(), value.clone()),
+                value => (key.clone(), value.to_string()),
+            })
+            .collect()
+    }
 }
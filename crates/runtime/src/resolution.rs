@@ -1,5 +1,67 @@
+use std::fmt;
+
+use crate::error::Error;
+use crate::{ActiveProxy, Runtime};
+
 #[derive(Debug)]
 pub enum Resolution {
-    Forward(rama::http::Request),
+    Forward(Forward),
     Respond(rama::http::Response),
 }
+
+#[derive(Debug)]
+pub struct Forward {
+    pub request: rama::http::Request,
+    /// Upstream chosen by the component, `None` dials the request URI.
+    pub target: Option<Target>,
+    pub response_hook: Option<Box<ResponseHook>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Target {
+    Address(String),
+    Upstream(String),
+}
+
+/// The components of the chain exporting `on-response`, instantiated again
+/// once the upstream answered. Their instances from handling the request are
+/// already released by then, so no state carries over.
+pub struct ResponseHook {
+    pub(crate) runtime: Runtime,
+    /// In the order they handled the request.
+    pub(crate) proxies: Vec<ActiveProxy>,
+    /// Head of the forwarded request, changes a hook makes to it are dropped.
+    pub(crate) request: rama::http::Request,
+    pub(crate) request_id: String,
+}
+
+impl ResponseHook {
+    /// Runs the hooks in reverse order, each sees the response as modified by
+    /// the components after it in the chain.
+    pub async fn process(
+        self,
+        mut response: rama::http::Response,
+    ) -> Result<rama::http::Response, Error> {
+        for proxy in self.proxies.iter().rev() {
+            response = match self
+                .runtime
+                .on_response(proxy, &self.request, response, &self.request_id)
+                .await
+            {
+                Ok(response) => response,
+                Err(error) => {
+                    self.runtime.stats.record_error(&error);
+                    self.runtime.monitor.record(proxy.tag.as_deref(), &error);
+                    return Err(error);
+                }
+            };
+        }
+        Ok(response)
+    }
+}
+
+impl fmt::Debug for ResponseHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ResponseHook").finish_non_exhaustive()
+    }
+}
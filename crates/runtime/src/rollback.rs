@@ -0,0 +1,76 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast;
+
+use crate::configuration::rollback::Configuration;
+use crate::error::Error;
+
+/// Asks for the proxy `tag` to be replaced after it trapped too often.
+#[derive(Debug, Clone)]
+pub struct Rollback {
+    pub tag: String,
+    pub reason: String,
+}
+
+/// Counts the traps of every proxy within a sliding window.
+pub(crate) struct Monitor {
+    configuration: Option<Configuration>,
+    traps: Mutex<HashMap<String, VecDeque<Instant>>>,
+    sender: broadcast::Sender<Rollback>,
+}
+
+impl Monitor {
+    pub(crate) fn new(configuration: Option<Configuration>) -> Self {
+        let (sender, _) = broadcast::channel(16);
+        Self {
+            configuration,
+            traps: Default::default(),
+            sender,
+        }
+    }
+
+    pub(crate) fn subscribe(&self) -> broadcast::Receiver<Rollback> {
+        self.sender.subscribe()
+    }
+
+    /// The built-in default component has no tag and is never rolled back.
+    pub(crate) fn record(&self, tag: Option<&str>, error: &Error) {
+        let (Some(configuration), Some(tag)) = (&self.configuration, tag) else {
+            return;
+        };
+        let Ok(mut traps) = self.traps.lock() else {
+            return;
+        };
+        let window = Duration::from_millis(configuration.window_ms);
+        let now = Instant::now();
+        let trapped_at = traps.entry(tag.to_string()).or_default();
+        trapped_at.push_back(now);
+        while trapped_at
+            .front()
+            .is_some_and(|instant| now.duration_since(*instant) > window)
+        {
+            trapped_at.pop_front();
+        }
+        if trapped_at.len() < configuration.max_traps {
+            return;
+        }
+        traps.remove(tag);
+        let reason = format!(
+            "{} traps within {} ms, last: {}",
+            configuration.max_traps, configuration.window_ms, error
+        );
+        tracing::warn!(tag, reason, "Requesting rollback of component");
+        let _ = self.sender.send(Rollback {
+            tag: tag.to_string(),
+            reason,
+        });
+    }
+
+    /// Drops the traps counted for a proxy that was deleted.
+    pub(crate) fn forget(&self, tag: &str) {
+        if let Ok(mut traps) = self.traps.lock() {
+            traps.remove(tag);
+        }
+    }
+}
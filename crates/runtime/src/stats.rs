@@ -0,0 +1,59 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::error::Error;
+use crate::resolution::Resolution;
+
+/// Counters of request outcomes since the gateway started.
+#[derive(Default)]
+pub(crate) struct Stats {
+    requests: AtomicU64,
+    forwarded: AtomicU64,
+    responded: AtomicU64,
+    timeouts: AtomicU64,
+    memory_limit_hits: AtomicU64,
+    failures: AtomicU64,
+}
+
+#[derive(Debug, Clone, Copy, serde::Serialize)]
+pub struct Snapshot {
+    pub requests: u64,
+    pub forwarded: u64,
+    pub responded: u64,
+    pub timeouts: u64,
+    pub memory_limit_hits: u64,
+    pub failures: u64,
+}
+
+impl Stats {
+    pub(crate) fn record(&self, result: &Result<Resolution, Error>) {
+        self.requests.fetch_add(1, Ordering::Relaxed);
+        match result {
+            Ok(Resolution::Forward(_)) => {
+                self.forwarded.fetch_add(1, Ordering::Relaxed);
+            }
+            Ok(Resolution::Respond(_)) => {
+                self.responded.fetch_add(1, Ordering::Relaxed);
+            }
+            Err(error) => self.record_error(error),
+        }
+    }
+
+    pub(crate) fn record_error(&self, error: &Error) {
+        match error {
+            Error::Timeout => self.timeouts.fetch_add(1, Ordering::Relaxed),
+            Error::MemoryLimit(_) => self.memory_limit_hits.fetch_add(1, Ordering::Relaxed),
+            Error::Failed(_) => self.failures.fetch_add(1, Ordering::Relaxed),
+        };
+    }
+
+    pub(crate) fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            requests: self.requests.load(Ordering::Relaxed),
+            forwarded: self.forwarded.load(Ordering::Relaxed),
+            responded: self.responded.load(Ordering::Relaxed),
+            timeouts: self.timeouts.load(Ordering::Relaxed),
+            memory_limit_hits: self.memory_limit_hits.load(Ordering::Relaxed),
+            failures: self.failures.load(Ordering::Relaxed),
+        }
+    }
+}
@@ -0,0 +1,160 @@
+use anyhow::{Result, bail};
+use std::fmt;
+use wasmtime::Engine;
+use wasmtime::component::types::ComponentItem;
+use wasmtime::component::{Component, Linker, LinkerInstance, ResourceType};
+
+use crate::context::Context;
+use crate::prepared::WorldVersion;
+
+const CROSSROADS_PACKAGE: &str = "wit:crossroads/";
+
+/// Why a component cannot serve requests.
+#[derive(Debug, Default, serde::Serialize)]
+pub struct Incompatible {
+    pub missing_exports: Vec<String>,
+    pub unsatisfied_imports: Vec<String>,
+    /// Failures compiling or linking the component.
+    pub errors: Vec<String>,
+}
+
+impl Incompatible {
+    pub(crate) fn error(error: anyhow::Error) -> Self {
+        Self {
+            errors: vec![format!("{:#}", error)],
+            ..Default::default()
+        }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.missing_exports.is_empty()
+            && self.unsatisfied_imports.is_empty()
+            && self.errors.is_empty()
+    }
+}
+
+impl fmt::Display for Incompatible {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut problems = Vec::new();
+        if !self.missing_exports.is_empty() {
+            problems.push(format!(
+                "missing exports: {}",
+                self.missing_exports.join(", ")
+            ));
+        }
+        if !self.unsatisfied_imports.is_empty() {
+            problems.push(format!(
+                "unsatisfied imports: {}",
+                self.unsatisfied_imports.join(", ")
+            ));
+        }
+        problems.extend(self.errors.iter().cloned());
+        write!(f, "Incompatible component, {}", problems.join("; "))
+    }
+}
+
+impl std::error::Error for Incompatible {}
+
+/// Imports of crossroads interfaces of another version than the world the
+/// component exports, and imports the linker does not satisfy.
+pub(crate) fn unsatisfied_imports(linker: &Linker<Context>, component: &Component) -> Vec<String> {
+    let engine = linker.engine();
+    let world_version = WorldVersion::detect(engine, component).map(|(version, _)| version);
+    let component_type = component.component_type();
+    let imports = component_type.imports(engine).collect::<Vec<_>>();
+    let unlinked = unlinked_imports(linker, component, &imports);
+    imports
+        .iter()
+        .enumerate()
+        .filter(|(index, (name, _))| {
+            let other_version = name
+                .strip_prefix(CROSSROADS_PACKAGE)
+                .and_then(|interface| interface.split_once('@'))
+                .is_some_and(|(_, version)| {
+                    world_version.is_some_and(|world| WorldVersion::parse(version) != Some(world))
+                });
+            other_version || unlinked.contains(index)
+        })
+        .map(|(_, (name, _))| name.to_string())
+        .collect()
+}
+
+/// Indices of the imports the linker satisfies neither directly nor through
+/// a semver compatible version. Linking checks the imports in order and stops
+/// at the first one it cannot satisfy, so that one is replaced by a stub that
+/// always links and linking resumes, once per unsatisfied import.
+fn unlinked_imports(
+    linker: &Linker<Context>,
+    component: &Component,
+    imports: &[(&str, ComponentItem)],
+) -> Vec<usize> {
+    let engine = linker.engine();
+    let mut linker = linker.clone();
+    linker.allow_shadowing(true);
+    let mut resources = Vec::new();
+    let mut recorded = 0;
+    let mut unlinked = Vec::new();
+    while let Err(error) = linker.instantiate_pre(component) {
+        // The error names the import as `name`, the ones before it linked.
+        let message = error.to_string();
+        let Some(index) = imports
+            .iter()
+            .position(|(name, _)| message.contains(&format!("`{}`", name)))
+            .filter(|index| *index >= recorded)
+        else {
+            break;
+        };
+        for (_, item) in &imports[recorded..index] {
+            record_resources(item, engine, &mut resources);
+        }
+        recorded = index + 1;
+        unlinked.push(index);
+        let (name, item) = &imports[index];
+        if stub(&mut linker.root(), name, item, engine, &mut resources).is_err() {
+            break;
+        }
+    }
+    unlinked
+}
+
+/// Defines `item` with functions and resources that are never used. Resources
+/// seen before are aliases of an earlier import and stay undefined.
+fn stub(
+    instance: &mut LinkerInstance<'_, Context>,
+    name: &str,
+    item: &ComponentItem,
+    engine: &Engine,
+    resources: &mut Vec<ResourceType>,
+) -> Result<()> {
+    match item {
+        ComponentItem::ComponentFunc(_) => instance.func_new(name, |_, _, _| Ok(())),
+        ComponentItem::ComponentInstance(ty) => {
+            let mut nested = instance.instance(name)?;
+            for (export, item) in ty.exports(engine) {
+                stub(&mut nested, export, &item, engine, resources)?;
+            }
+            Ok(())
+        }
+        ComponentItem::Resource(ty) if resources.contains(ty) => Ok(()),
+        ComponentItem::Resource(ty) => {
+            resources.push(*ty);
+            instance.resource(name, ResourceType::host::<()>(), |_, _| Ok(()))
+        }
+        ComponentItem::Module(_) | ComponentItem::Component(_) => {
+            bail!("Cannot stub import {}", name)
+        }
+        ComponentItem::CoreFunc(_) | ComponentItem::Type(_) => Ok(()),
+    }
+}
+
+fn record_resources(item: &ComponentItem, engine: &Engine, resources: &mut Vec<ResourceType>) {
+    match item {
+        ComponentItem::ComponentInstance(ty) => {
+            for (_, item) in ty.exports(engine) {
+                record_resources(&item, engine, resources);
+            }
+        }
+        ComponentItem::Resource(ty) if !resources.contains(ty) => resources.push(*ty),
+        _ => {}
+    }
+}
@@ -0,0 +1,114 @@
+mod common;
+
+use anyhow::Result;
+use rama::http::Body;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use common::{component, forwarded, request, runtime};
+use runtime::configuration::{Configuration, cache};
+use runtime::proxy::Proxy;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn compiled_component_is_stored_and_reused() -> Result<()> {
+    let directory = tempfile::tempdir()?;
+    let configuration = configuration(directory.path());
+
+    runtime(&configuration, "forward")?;
+    let artifacts = files(directory.path())?;
+    assert_eq!(artifacts.len(), 1);
+    assert!(
+        artifacts[0]
+            .extension()
+            .is_some_and(|extension| extension == "cwasm")
+    );
+
+    let runtime = runtime(&configuration, "forward")?;
+    assert_eq!(files(directory.path())?, artifacts);
+    forwarded(runtime.process(request(Body::empty())?).await?)?;
+
+    Ok(())
+}
+
+#[test]
+fn artifact_of_forgotten_component_is_removed() -> Result<()> {
+    let directory = tempfile::tempdir()?;
+    let runtime = runtime(&configuration(directory.path()), "forward")?;
+    let default = files(directory.path())?;
+
+    runtime.set_proxy(&Proxy::new("respond".to_string(), component("respond")))?;
+    assert_eq!(files(directory.path())?.len(), 2);
+
+    runtime.forget(&component("respond"));
+    assert_eq!(files(directory.path())?, default);
+
+    Ok(())
+}
+
+#[test]
+fn validated_component_is_not_stored() -> Result<()> {
+    let directory = tempfile::tempdir()?;
+    let runtime = runtime(&configuration(directory.path()), "forward")?;
+    let default = files(directory.path())?;
+
+    assert!(runtime.validate(&component("respond")).is_ok());
+    assert_eq!(files(directory.path())?, default);
+
+    Ok(())
+}
+
+#[test]
+fn validated_component_is_stored_once_loaded() -> Result<()> {
+    let directory = tempfile::tempdir()?;
+    let runtime = runtime(&configuration(directory.path()), "forward")?;
+    let default = files(directory.path())?;
+
+    assert!(runtime.validate(&component("respond")).is_ok());
+    runtime.set_proxy(&Proxy::new("respond".to_string(), component("respond")))?;
+    assert_eq!(files(directory.path())?.len(), default.len() + 1);
+
+    Ok(())
+}
+
+#[test]
+fn stale_and_abandoned_artifacts_are_removed() -> Result<()> {
+    let directory = tempfile::tempdir()?;
+    let stale = directory.path().join("0123-4567.cwasm");
+    let abandoned = directory.path().join("89ab-cdef.partial");
+    let partial = directory.path().join(".tmp1234.partial");
+    let unrelated = directory.path().join("README");
+    for file in [&stale, &abandoned, &partial, &unrelated] {
+        fs::write(file, b"")?;
+    }
+    let day_ago = SystemTime::now() - Duration::from_secs(24 * 60 * 60);
+    File::options()
+        .write(true)
+        .open(&abandoned)?
+        .set_modified(day_ago)?;
+
+    runtime(&configuration(directory.path()), "forward")?;
+    assert!(!stale.exists());
+    assert!(!abandoned.exists());
+    assert!(partial.exists(), "Artifact still being written was removed");
+    assert!(unrelated.exists());
+
+    Ok(())
+}
+
+fn configuration(directory: &Path) -> Configuration {
+    Configuration {
+        cache: Some(cache::Configuration {
+            directory: directory.to_path_buf(),
+        }),
+        ..Default::default()
+    }
+}
+
+fn files(directory: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = fs::read_dir(directory)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+    files.sort();
+    Ok(files)
+}
@@ -0,0 +1,115 @@
+mod common;
+
+use anyhow::{Result, bail};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use common::{collect, component, forwarded, request, runtime};
+use runtime::configuration::{Configuration, capabilities, proxy};
+use runtime::proxy::Proxy;
+use runtime::resolution::Resolution;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn environment_is_denied_by_default() -> Result<()> {
+    let runtime = runtime(&Default::default(), "environment")?;
+
+    forwarded(runtime.process(request("")?).await?)?;
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn granted_environment_variable_is_passed_on() -> Result<()> {
+    let configuration = Configuration {
+        capabilities: capabilities::Configuration {
+            env: vec!["CARGO_PKG_NAME".to_string()],
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let runtime = runtime(&configuration, "environment")?;
+
+    let Resolution::Respond(response) = runtime.process(request("")?).await? else {
+        bail!("Component did not see the granted variable");
+    };
+    assert_eq!(
+        collect(response.into_body()).await?,
+        env!("CARGO_PKG_NAME").as_bytes()
+    );
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn proxy_policy_replaces_global_one() -> Result<()> {
+    let configuration = Configuration {
+        capabilities: capabilities::Configuration {
+            env: vec!["CARGO_PKG_NAME".to_string()],
+            ..Default::default()
+        },
+        proxies: HashMap::from([(
+            "env:v1".to_string(),
+            proxy::Configuration {
+                capabilities: Some(Default::default()),
+                ..Default::default()
+            },
+        )]),
+        ..Default::default()
+    };
+    let runtime = runtime(&configuration, "forward")?;
+    runtime.set_proxy(&Proxy::new("env:v1".to_string(), component("environment")))?;
+
+    forwarded(runtime.process(request("")?).await?)?;
+
+    Ok(())
+}
+
+#[test]
+fn missing_preopen_fails_at_startup() {
+    let configuration = with_preopen(PathBuf::from("/nonexistent/crossroads"));
+
+    assert!(runtime(&configuration, "forward").is_err());
+}
+
+#[test]
+fn missing_preopen_of_proxy_fails_to_load() -> Result<()> {
+    let missing = with_preopen(PathBuf::from("/nonexistent/crossroads"));
+    let configuration = Configuration {
+        proxies: HashMap::from([(
+            "env:v1".to_string(),
+            proxy::Configuration {
+                capabilities: Some(missing.capabilities),
+                ..Default::default()
+            },
+        )]),
+        ..Default::default()
+    };
+    let runtime = runtime(&configuration, "forward")?;
+
+    let proxy = Proxy::new("env:v1".to_string(), component("environment"));
+    assert!(runtime.set_proxy(&proxy).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn existing_preopen_is_accepted() -> Result<()> {
+    let directory = tempfile::tempdir()?;
+
+    runtime(&with_preopen(directory.path().to_path_buf()), "forward")?;
+
+    Ok(())
+}
+
+fn with_preopen(host: PathBuf) -> Configuration {
+    Configuration {
+        capabilities: capabilities::Configuration {
+            preopens: vec![capabilities::Preopen {
+                host,
+                guest: "/data".to_string(),
+            }],
+            ..Default::default()
+        },
+        ..Default::default()
+    }
+}
@@ -0,0 +1,170 @@
+use anyhow::Result;
+use rama::http::{Body, Request};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+use runtime::client::{Client, Error};
+use runtime::configuration::client::Configuration;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn allowed_host_is_called() -> Result<()> {
+    let stub = Stub::respond("hello").await?;
+    let mut client = Client::new(configuration(&[stub.authority().as_str()]));
+
+    let response = client.send(stub.request()?).await?;
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.body().as_slice(), b"hello");
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn host_not_on_allowlist_is_rejected() -> Result<()> {
+    let stub = Stub::respond("hello").await?;
+    let mut client = Client::new(configuration(&["example.com"]));
+
+    let error = client.send(stub.request()?).await.unwrap_err();
+    assert_eq!(error, Error::HostNotAllowed(stub.authority()));
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn host_without_port_allows_only_the_default_port() -> Result<()> {
+    let stub = Stub::respond("hello").await?;
+    let mut client = Client::new(configuration(&["127.0.0.1"]));
+
+    let error = client.send(stub.request()?).await.unwrap_err();
+    assert_eq!(error, Error::HostNotAllowed(stub.authority()));
+
+    Ok(())
+}
+
+#[test]
+fn port_of_entry_has_to_match() -> Result<()> {
+    let client = Client::new(configuration(&["api.example.com:8080"]));
+
+    assert!(client.is_allowed(&"http://api.example.com:8080/".parse()?));
+    assert!(!client.is_allowed(&"http://api.example.com/".parse()?));
+    assert!(!client.is_allowed(&"http://api.example.com:9090/".parse()?));
+
+    Ok(())
+}
+
+#[test]
+fn entry_without_port_follows_the_scheme() -> Result<()> {
+    let client = Client::new(configuration(&["api.example.com"]));
+
+    assert!(client.is_allowed(&"http://api.example.com/".parse()?));
+    assert!(client.is_allowed(&"https://api.example.com/".parse()?));
+    assert!(client.is_allowed(&"https://api.example.com:443/".parse()?));
+    assert!(!client.is_allowed(&"https://api.example.com:80/".parse()?));
+
+    Ok(())
+}
+
+#[test]
+fn wildcard_matches_subdomains_only() -> Result<()> {
+    let client = Client::new(configuration(&["*.example.com"]));
+
+    assert!(client.is_allowed(&"http://api.example.com/".parse()?));
+    assert!(!client.is_allowed(&"http://example.com/".parse()?));
+    assert!(!client.is_allowed(&"http://badexample.com/".parse()?));
+
+    Ok(())
+}
+
+#[test]
+fn wildcard_ignores_case() -> Result<()> {
+    let client = Client::new(configuration(&["*.Example.com"]));
+
+    assert!(client.is_allowed(&"http://API.example.COM/".parse()?));
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn call_budget_is_enforced() -> Result<()> {
+    let stub = Stub::respond("hello").await?;
+    let mut configuration = configuration(&[stub.authority().as_str()]);
+    configuration.max_calls = 2;
+    let mut client = Client::new(configuration);
+
+    client.send(stub.request()?).await?;
+    client.send(stub.request()?).await?;
+    let error = client.send(stub.request()?).await.unwrap_err();
+    assert_eq!(error, Error::BudgetExhausted);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn slow_upstream_times_out() -> Result<()> {
+    let stub = Stub::hang().await?;
+    let mut configuration = configuration(&[stub.authority().as_str()]);
+    configuration.timeout_ms = 100;
+    let mut client = Client::new(configuration);
+
+    let error = client.send(stub.request()?).await.unwrap_err();
+    assert_eq!(error, Error::Timeout);
+
+    Ok(())
+}
+
+fn configuration(allowed_hosts: &[&str]) -> Configuration {
+    Configuration {
+        allowed_hosts: allowed_hosts.iter().map(|host| host.to_string()).collect(),
+        ..Default::default()
+    }
+}
+
+/// Minimal HTTP/1.1 server answering every connection the same way.
+struct Stub {
+    port: u16,
+}
+
+impl Stub {
+    async fn respond(body: &'static str) -> Result<Self> {
+        Self::spawn(Some(body)).await
+    }
+
+    async fn hang() -> Result<Self> {
+        Self::spawn(None).await
+    }
+
+    async fn spawn(body: Option<&'static str>) -> Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let port = listener.local_addr()?.port();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                tokio::spawn(async move {
+                    let mut buffer = [0; 4096];
+                    let _ = stream.read(&mut buffer).await;
+                    match body {
+                        Some(body) => {
+                            let response = format!(
+                                "HTTP/1.1 200 OK\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                                body.len(),
+                                body
+                            );
+                            let _ = stream.write_all(response.as_bytes()).await;
+                        }
+                        None => tokio::time::sleep(std::time::Duration::from_secs(60)).await,
+                    }
+                });
+            }
+        });
+        Ok(Self { port })
+    }
+
+    fn authority(&self) -> String {
+        format!("127.0.0.1:{}", self.port)
+    }
+
+    fn request(&self) -> Result<Request> {
+        let request = Request::builder()
+            .uri(format!("http://{}/", self.authority()))
+            .body(Body::empty())?;
+        Ok(request)
+    }
+}
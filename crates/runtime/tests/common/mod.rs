@@ -0,0 +1,142 @@
+//! Helpers shared by the runtime tests, guest components are written in the
+//! WebAssembly text format under `tests/components`.
+#![allow(dead_code)]
+
+use anyhow::{Result, anyhow};
+use bytes::Bytes;
+use rama::http::dep::http_body::{self, Frame};
+use rama::http::dep::http_body_util::BodyExt;
+use rama::http::{Body, Request};
+use std::collections::{HashMap, VecDeque};
+use std::convert::Infallible;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use runtime::Runtime;
+use runtime::configuration::Configuration;
+use runtime::kv::KeyValueStore;
+use runtime::resolution::{Forward, Resolution};
+
+/// Encodes the test component `tests/components/<name>.wat`.
+pub fn component(name: &str) -> Vec<u8> {
+    let path = format!(
+        "{}/tests/components/{}.wat",
+        env!("CARGO_MANIFEST_DIR"),
+        name
+    );
+    wat::parse_file(&path).unwrap_or_else(|e| panic!("Invalid component {}: {}", name, e))
+}
+
+/// Runtime serving the test component `name` as its default proxy.
+pub fn runtime(configuration: &Configuration, name: &str) -> Result<Runtime> {
+    runtime_with_store(configuration, name, Arc::new(MemoryStore::default()))
+}
+
+pub fn runtime_with_store(
+    configuration: &Configuration,
+    name: &str,
+    store: Arc<dyn KeyValueStore>,
+) -> Result<Runtime> {
+    Runtime::new(configuration, store, &component(name))
+}
+
+pub fn request(body: impl Into<Body>) -> Result<Request> {
+    let request = Request::builder()
+        .uri("http://example.com/")
+        .body(body.into())?;
+    Ok(request)
+}
+
+pub fn forwarded(resolution: Resolution) -> Result<Forward> {
+    match resolution {
+        Resolution::Forward(forward) => Ok(forward),
+        Resolution::Respond(response) => Err(anyhow!("Expected forward, got {:?}", response)),
+    }
+}
+
+pub async fn collect(body: Body) -> Result<Vec<u8>> {
+    Ok(body.collect().await?.to_bytes().to_vec())
+}
+
+/// Body without a length, yielding one frame per chunk.
+pub struct Chunks(VecDeque<Bytes>);
+
+impl Chunks {
+    pub fn new(chunks: &[&'static str]) -> Self {
+        Self(chunks.iter().map(|chunk| Bytes::from(*chunk)).collect())
+    }
+}
+
+impl http_body::Body for Chunks {
+    type Data = Bytes;
+    type Error = Infallible;
+
+    fn poll_frame(
+        mut self: Pin<&mut Self>,
+        _: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        Poll::Ready(self.0.pop_front().map(|chunk| Ok(Frame::data(chunk))))
+    }
+}
+
+type Values = HashMap<(String, String), Vec<u8>>;
+
+/// Key-value store kept in memory, every call takes at least `delay`.
+#[derive(Default)]
+pub struct MemoryStore {
+    values: Mutex<Values>,
+    delay: Duration,
+}
+
+impl MemoryStore {
+    pub fn slow(delay: Duration) -> Self {
+        Self {
+            values: Default::default(),
+            delay,
+        }
+    }
+
+    fn values(&self) -> Result<std::sync::MutexGuard<'_, Values>> {
+        self.values
+            .lock()
+            .map_err(|e| anyhow!("Failed to acquire lock of values: {}", e))
+    }
+}
+
+#[async_trait::async_trait]
+impl KeyValueStore for MemoryStore {
+    async fn get(&self, namespace: &str, key: &str) -> Result<Option<Vec<u8>>> {
+        tokio::time::sleep(self.delay).await;
+        let key = (namespace.to_string(), key.to_string());
+        Ok(self.values()?.get(&key).cloned())
+    }
+
+    async fn set(&self, namespace: &str, key: &str, value: Vec<u8>) -> Result<()> {
+        tokio::time::sleep(self.delay).await;
+        let key = (namespace.to_string(), key.to_string());
+        self.values()?.insert(key, value);
+        Ok(())
+    }
+
+    async fn delete(&self, namespace: &str, key: &str) -> Result<()> {
+        tokio::time::sleep(self.delay).await;
+        let key = (namespace.to_string(), key.to_string());
+        self.values()?.remove(&key);
+        Ok(())
+    }
+
+    async fn increment(&self, namespace: &str, key: &str, delta: i64) -> Result<i64> {
+        tokio::time::sleep(self.delay).await;
+        let key = (namespace.to_string(), key.to_string());
+        let mut values = self.values()?;
+        let current = match values.get(&key) {
+            Some(value) => String::from_utf8(value.clone())?.parse::<i64>()?,
+            None => 0,
+        };
+        let value = current + delta;
+        values.insert(key, value.to_string().into_bytes());
+        Ok(value)
+    }
+}
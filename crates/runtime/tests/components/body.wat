@@ -0,0 +1,65 @@
+;; Responds with the request body if it fits into 8 bytes, forwards the
+;; request otherwise.
+(component
+  (type $target (variant (case "address" string) (case "upstream" string)))
+  (type $response (record
+    (field "status-code" u16)
+    (field "headers" (list (tuple string (list u8))))
+    (field "body" (option (list u8)))))
+  (type $resolution (variant
+    (case "forward")
+    (case "forward-to" $target)
+    (case "respond" $response)))
+
+  (import "wit:crossroads/request@0.2.0" (instance $request
+    (export "body" (func (param "limit" u64) (result (result (list u8) (error string)))))))
+
+  (core module $memory
+    (memory (export "memory") 1)
+    (global $next (mut i32) (i32.const 1024))
+    ;; Bump allocator, nothing is ever freed.
+    (func (export "realloc") (param i32 i32 i32 i32) (result i32)
+      (local $pointer i32)
+      (local.set $pointer
+        (i32.and
+          (i32.add (global.get $next) (i32.sub (local.get 2) (i32.const 1)))
+          (i32.sub (i32.const 0) (local.get 2))))
+      (global.set $next (i32.add (local.get $pointer) (local.get 3)))
+      (local.get $pointer)))
+  (core instance $memory (instantiate $memory))
+  (alias core export $memory "memory" (core memory $memory))
+  (alias core export $memory "realloc" (core func $realloc))
+
+  (core func $body (canon lower (func $request "body") (memory $memory) (realloc $realloc)))
+
+  (core module $guest
+    (import "host" "memory" (memory 1))
+    (import "host" "body" (func $body (param i64 i32)))
+    (func (export "handle") (result i32)
+      (call $body (i64.const 8) (i32.const 0))
+      (if (i32.load8_u (i32.const 0))
+        (then
+          (i32.store8 (i32.const 16) (i32.const 0))
+          (return (i32.const 16))))
+      ;; respond(response { status-code: 200, headers: [], body: some(body) })
+      (i32.store8 (i32.const 16) (i32.const 2))
+      (i32.store16 (i32.const 20) (i32.const 200))
+      (i32.store (i32.const 24) (i32.const 0))
+      (i32.store (i32.const 28) (i32.const 0))
+      (i32.store8 (i32.const 32) (i32.const 1))
+      (i32.store (i32.const 36) (i32.load (i32.const 4)))
+      (i32.store (i32.const 40) (i32.load (i32.const 8)))
+      (i32.const 16)))
+  (core instance $guest (instantiate $guest
+    (with "host" (instance
+      (export "memory" (memory $memory))
+      (export "body" (func $body))))))
+
+  (func $handle (result $resolution)
+    (canon lift (core func $guest "handle") (memory $memory)))
+  (instance $proxy
+    (export "target" (type $target))
+    (export "response" (type $response))
+    (export "resolution" (type $resolution))
+    (export "handle" (func $handle)))
+  (export "wit:crossroads/proxy@0.2.0" (instance $proxy)))
@@ -0,0 +1,67 @@
+;; Increments the key `hits` of the key-value store and forwards the request,
+;; responds with status 500 if the increment fails.
+(component
+  (type $target (variant (case "address" string) (case "upstream" string)))
+  (type $response (record
+    (field "status-code" u16)
+    (field "headers" (list (tuple string (list u8))))
+    (field "body" (option (list u8)))))
+  (type $resolution (variant
+    (case "forward")
+    (case "forward-to" $target)
+    (case "respond" $response)))
+
+  (import "wit:crossroads/kv@0.2.0" (instance $kv
+    (export "increment" (func
+      (param "key" string) (param "delta" s64)
+      (result (result s64 (error string)))))))
+
+  (core module $memory
+    (memory (export "memory") 1)
+    (global $next (mut i32) (i32.const 1024))
+    ;; Bump allocator, nothing is ever freed.
+    (func (export "realloc") (param i32 i32 i32 i32) (result i32)
+      (local $pointer i32)
+      (local.set $pointer
+        (i32.and
+          (i32.add (global.get $next) (i32.sub (local.get 2) (i32.const 1)))
+          (i32.sub (i32.const 0) (local.get 2))))
+      (global.set $next (i32.add (local.get $pointer) (local.get 3)))
+      (local.get $pointer)))
+  (core instance $memory (instantiate $memory))
+  (alias core export $memory "memory" (core memory $memory))
+  (alias core export $memory "realloc" (core func $realloc))
+
+  (core func $increment (canon lower (func $kv "increment")
+    (memory $memory) (realloc $realloc)))
+
+  (core module $guest
+    (import "host" "memory" (memory 1))
+    (import "host" "increment" (func $increment (param i32 i32 i64 i32)))
+    (data (i32.const 100) "hits")
+    (func (export "handle") (result i32)
+      (call $increment (i32.const 100) (i32.const 4) (i64.const 1) (i32.const 0))
+      (if (i32.load8_u (i32.const 0))
+        (then
+          ;; respond(response { status-code: 500, headers: [], body: none })
+          (i32.store8 (i32.const 32) (i32.const 2))
+          (i32.store16 (i32.const 36) (i32.const 500))
+          (i32.store (i32.const 40) (i32.const 0))
+          (i32.store (i32.const 44) (i32.const 0))
+          (i32.store8 (i32.const 48) (i32.const 0))
+          (return (i32.const 32))))
+      (i32.store8 (i32.const 32) (i32.const 0))
+      (i32.const 32)))
+  (core instance $guest (instantiate $guest
+    (with "host" (instance
+      (export "memory" (memory $memory))
+      (export "increment" (func $increment))))))
+
+  (func $handle (result $resolution)
+    (canon lift (core func $guest "handle") (memory $memory)))
+  (instance $proxy
+    (export "target" (type $target))
+    (export "response" (type $response))
+    (export "resolution" (type $resolution))
+    (export "handle" (func $handle)))
+  (export "wit:crossroads/proxy@0.2.0" (instance $proxy)))
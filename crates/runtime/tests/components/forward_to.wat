@@ -0,0 +1,32 @@
+;; Forwards every request to the upstream `backend`.
+(component
+  (type $target (variant (case "address" string) (case "upstream" string)))
+  (type $response (record
+    (field "status-code" u16)
+    (field "headers" (list (tuple string (list u8))))
+    (field "body" (option (list u8)))))
+  (type $resolution (variant
+    (case "forward")
+    (case "forward-to" $target)
+    (case "respond" $response)))
+
+  (core module $guest
+    (memory (export "memory") 1)
+    (data (i32.const 100) "backend")
+    (func (export "handle") (result i32)
+      ;; forward-to(upstream("backend"))
+      (i32.store8 (i32.const 0) (i32.const 1))
+      (i32.store8 (i32.const 4) (i32.const 1))
+      (i32.store (i32.const 8) (i32.const 100))
+      (i32.store (i32.const 12) (i32.const 7))
+      (i32.const 0)))
+  (core instance $guest (instantiate $guest))
+
+  (func $handle (result $resolution)
+    (canon lift (core func $guest "handle") (memory (core memory $guest "memory"))))
+  (instance $proxy
+    (export "target" (type $target))
+    (export "response" (type $response))
+    (export "resolution" (type $resolution))
+    (export "handle" (func $handle)))
+  (export "wit:crossroads/proxy@0.2.0" (instance $proxy)))
@@ -0,0 +1,70 @@
+;; Counts the values of the request header `x-in` into `x-count` and appends
+;; the header `x-raw` with a value that is not valid UTF-8.
+(component
+  (type $target (variant (case "address" string) (case "upstream" string)))
+  (type $response (record
+    (field "status-code" u16)
+    (field "headers" (list (tuple string (list u8))))
+    (field "body" (option (list u8)))))
+  (type $resolution (variant
+    (case "forward")
+    (case "forward-to" $target)
+    (case "respond" $response)))
+
+  (import "wit:crossroads/request@0.2.0" (instance $request
+    (export "header" (func (param "name" string) (result (list (list u8)))))
+    (export "append-header" (func
+      (param "name" string) (param "value" (list u8))
+      (result (result (error string)))))))
+
+  (core module $memory
+    (memory (export "memory") 1)
+    (global $next (mut i32) (i32.const 1024))
+    ;; Bump allocator, nothing is ever freed.
+    (func (export "realloc") (param i32 i32 i32 i32) (result i32)
+      (local $pointer i32)
+      (local.set $pointer
+        (i32.and
+          (i32.add (global.get $next) (i32.sub (local.get 2) (i32.const 1)))
+          (i32.sub (i32.const 0) (local.get 2))))
+      (global.set $next (i32.add (local.get $pointer) (local.get 3)))
+      (local.get $pointer)))
+  (core instance $memory (instantiate $memory))
+  (alias core export $memory "memory" (core memory $memory))
+  (alias core export $memory "realloc" (core func $realloc))
+
+  (core func $header (canon lower (func $request "header")
+    (memory $memory) (realloc $realloc)))
+  (core func $append-header (canon lower (func $request "append-header")
+    (memory $memory) (realloc $realloc)))
+
+  (core module $guest
+    (import "host" "memory" (memory 1))
+    (import "host" "header" (func $header (param i32 i32 i32)))
+    (import "host" "append-header" (func $append-header (param i32 i32 i32 i32 i32)))
+    (data (i32.const 100) "x-in")
+    (data (i32.const 110) "x-count")
+    (data (i32.const 120) "x-raw")
+    (data (i32.const 130) "\ff\fe")
+    (func (export "handle") (result i32)
+      (call $header (i32.const 100) (i32.const 4) (i32.const 0))
+      ;; The number of values as a single ASCII digit.
+      (i32.store8 (i32.const 140) (i32.add (i32.const 48) (i32.load (i32.const 4))))
+      (call $append-header (i32.const 110) (i32.const 7) (i32.const 140) (i32.const 1) (i32.const 8))
+      (call $append-header (i32.const 120) (i32.const 5) (i32.const 130) (i32.const 2) (i32.const 8))
+      (i32.store8 (i32.const 16) (i32.const 0))
+      (i32.const 16)))
+  (core instance $guest (instantiate $guest
+    (with "host" (instance
+      (export "memory" (memory $memory))
+      (export "header" (func $header))
+      (export "append-header" (func $append-header))))))
+
+  (func $handle (result $resolution)
+    (canon lift (core func $guest "handle") (memory $memory)))
+  (instance $proxy
+    (export "target" (type $target))
+    (export "response" (type $response))
+    (export "resolution" (type $resolution))
+    (export "handle" (func $handle)))
+  (export "wit:crossroads/proxy@0.2.0" (instance $proxy)))
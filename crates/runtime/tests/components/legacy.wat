@@ -0,0 +1,55 @@
+;; Component of the first `wit:crossroads@0.1.0` world, sets the request
+;; header `x-legacy: 1` and forwards the request.
+(component
+  (type $response (record
+    (field "status-code" u16)
+    (field "body" (option (list u8)))))
+  (type $resolution (variant
+    (case "forward")
+    (case "respond" $response)))
+
+  (import "wit:crossroads/request@0.1.0" (instance $request
+    (export "set-header" (func
+      (param "key" string) (param "value" string)
+      (result (result (error string)))))))
+
+  (core module $memory
+    (memory (export "memory") 1)
+    (global $next (mut i32) (i32.const 1024))
+    ;; Bump allocator, nothing is ever freed.
+    (func (export "realloc") (param i32 i32 i32 i32) (result i32)
+      (local $pointer i32)
+      (local.set $pointer
+        (i32.and
+          (i32.add (global.get $next) (i32.sub (local.get 2) (i32.const 1)))
+          (i32.sub (i32.const 0) (local.get 2))))
+      (global.set $next (i32.add (local.get $pointer) (local.get 3)))
+      (local.get $pointer)))
+  (core instance $memory (instantiate $memory))
+  (alias core export $memory "memory" (core memory $memory))
+  (alias core export $memory "realloc" (core func $realloc))
+
+  (core func $set-header (canon lower (func $request "set-header")
+    (memory $memory) (realloc $realloc)))
+
+  (core module $guest
+    (import "host" "memory" (memory 1))
+    (import "host" "set-header" (func $set-header (param i32 i32 i32 i32 i32)))
+    (data (i32.const 100) "x-legacy")
+    (data (i32.const 110) "1")
+    (func (export "handle") (result i32)
+      (call $set-header (i32.const 100) (i32.const 8) (i32.const 110) (i32.const 1) (i32.const 0))
+      (i32.store8 (i32.const 16) (i32.const 0))
+      (i32.const 16)))
+  (core instance $guest (instantiate $guest
+    (with "host" (instance
+      (export "memory" (memory $memory))
+      (export "set-header" (func $set-header))))))
+
+  (func $handle (result $resolution)
+    (canon lift (core func $guest "handle") (memory $memory)))
+  (instance $proxy
+    (export "response" (type $response))
+    (export "resolution" (type $resolution))
+    (export "handle" (func $handle)))
+  (export "wit:crossroads/proxy@0.1.0" (instance $proxy)))
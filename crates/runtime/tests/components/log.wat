@@ -0,0 +1,57 @@
+;; Logs `denied` at level warn with the fields `user=alice` and `attempt=2`,
+;; then forwards the request.
+(component
+  (type $target (variant (case "address" string) (case "upstream" string)))
+  (type $response (record
+    (field "status-code" u16)
+    (field "headers" (list (tuple string (list u8))))
+    (field "body" (option (list u8)))))
+  (type $resolution (variant
+    (case "forward")
+    (case "forward-to" $target)
+    (case "respond" $response)))
+
+  (import "wit:crossroads/logging@0.2.0" (instance $logging
+    (type $level' (enum "trace" "debug" "info" "warn" "error"))
+    (export "level" (type $level (eq $level')))
+    (export "log" (func
+      (param "level" $level) (param "message" string)
+      (param "fields" (list (tuple string string)))))))
+
+  (core module $memory
+    (memory (export "memory") 1))
+  (core instance $memory (instantiate $memory))
+  (alias core export $memory "memory" (core memory $memory))
+
+  (core func $log (canon lower (func $logging "log")
+    (memory $memory) string-encoding=utf8))
+
+  (core module $guest
+    (import "host" "memory" (memory 1))
+    (import "host" "log" (func $log (param i32 i32 i32 i32 i32)))
+    (data (i32.const 100) "denied")
+    (data (i32.const 110) "user")
+    (data (i32.const 120) "alice")
+    (data (i32.const 130) "attempt")
+    (data (i32.const 140) "2")
+    ;; fields: [("user", "alice"), ("attempt", "2")]
+    (data (i32.const 200)
+      "\6e\00\00\00\04\00\00\00\78\00\00\00\05\00\00\00"
+      "\82\00\00\00\07\00\00\00\8c\00\00\00\01\00\00\00")
+    (func (export "handle") (result i32)
+      (call $log (i32.const 3) (i32.const 100) (i32.const 6) (i32.const 200) (i32.const 2))
+      (i32.store8 (i32.const 0) (i32.const 0))
+      (i32.const 0)))
+  (core instance $guest (instantiate $guest
+    (with "host" (instance
+      (export "memory" (memory $memory))
+      (export "log" (func $log))))))
+
+  (func $handle (result $resolution)
+    (canon lift (core func $guest "handle") (memory $memory)))
+  (instance $proxy
+    (export "target" (type $target))
+    (export "response" (type $response))
+    (export "resolution" (type $resolution))
+    (export "handle" (func $handle)))
+  (export "wit:crossroads/proxy@0.2.0" (instance $proxy)))
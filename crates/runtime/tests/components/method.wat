@@ -0,0 +1,91 @@
+;; Copies an extension method into the header `x-method` and turns the request
+;; into a GET, every other method is replaced by the extension method PURGE.
+;; The request is forwarded to the upstream `backend`.
+(component
+  (type $target (variant (case "address" string) (case "upstream" string)))
+  (type $response (record
+    (field "status-code" u16)
+    (field "headers" (list (tuple string (list u8))))
+    (field "body" (option (list u8)))))
+  (type $resolution (variant
+    (case "forward")
+    (case "forward-to" $target)
+    (case "respond" $response)))
+
+  (import "wit:crossroads/request@0.2.0" (instance $request
+    (type $http-method' (variant
+      (case "get") (case "head") (case "post") (case "put") (case "delete")
+      (case "connect") (case "options") (case "trace") (case "patch")
+      (case "other" string)))
+    (export "http-method" (type $http-method (eq $http-method')))
+    (export "method" (func (result $http-method)))
+    (export "set-method" (func (param "method" $http-method) (result (result (error string)))))
+    (export "append-header" (func
+      (param "name" string) (param "value" (list u8))
+      (result (result (error string)))))))
+
+  (core module $memory
+    (memory (export "memory") 1)
+    (global $next (mut i32) (i32.const 1024))
+    ;; Bump allocator, nothing is ever freed.
+    (func (export "realloc") (param i32 i32 i32 i32) (result i32)
+      (local $pointer i32)
+      (local.set $pointer
+        (i32.and
+          (i32.add (global.get $next) (i32.sub (local.get 2) (i32.const 1)))
+          (i32.sub (i32.const 0) (local.get 2))))
+      (global.set $next (i32.add (local.get $pointer) (local.get 3)))
+      (local.get $pointer)))
+  (core instance $memory (instantiate $memory))
+  (alias core export $memory "memory" (core memory $memory))
+  (alias core export $memory "realloc" (core func $realloc))
+
+  (core func $method (canon lower (func $request "method")
+    (memory $memory) (realloc $realloc)))
+  (core func $set-method (canon lower (func $request "set-method")
+    (memory $memory) (realloc $realloc)))
+  (core func $append-header (canon lower (func $request "append-header")
+    (memory $memory) (realloc $realloc)))
+
+  (core module $guest
+    (import "host" "memory" (memory 1))
+    (import "host" "method" (func $method (param i32)))
+    (import "host" "set-method" (func $set-method (param i32 i32 i32 i32)))
+    (import "host" "append-header" (func $append-header (param i32 i32 i32 i32 i32)))
+    (data (i32.const 100) "x-method")
+    (data (i32.const 110) "PURGE")
+    (data (i32.const 120) "backend")
+    (func (export "handle") (result i32)
+      (call $method (i32.const 0))
+      (if (i32.eq (i32.load8_u (i32.const 0)) (i32.const 9))
+        (then
+          (call $append-header
+            (i32.const 100) (i32.const 8)
+            (i32.load (i32.const 4)) (i32.load (i32.const 8))
+            (i32.const 16))
+          ;; set-method(get)
+          (call $set-method (i32.const 0) (i32.const 0) (i32.const 0) (i32.const 16)))
+        (else
+          ;; set-method(other("PURGE"))
+          (call $set-method (i32.const 9) (i32.const 110) (i32.const 5) (i32.const 16))))
+      ;; forward-to(upstream("backend"))
+      (i32.store8 (i32.const 32) (i32.const 1))
+      (i32.store8 (i32.const 36) (i32.const 1))
+      (i32.store (i32.const 40) (i32.const 120))
+      (i32.store (i32.const 44) (i32.const 7))
+      (i32.const 32)))
+  (core instance $guest (instantiate $guest
+    (with "host" (instance
+      (export "memory" (memory $memory))
+      (export "method" (func $method))
+      (export "set-method" (func $set-method))
+      (export "append-header" (func $append-header))))))
+
+  (func $handle (result $resolution)
+    (canon lift (core func $guest "handle") (memory $memory)))
+  (instance $proxy
+    (export "target" (type $target))
+    (export "response" (type $response))
+    (export "resolution" (type $resolution))
+    (export "handle" (func $handle)))
+  (export "wit:crossroads/proxy@0.2.0" (instance $proxy)))
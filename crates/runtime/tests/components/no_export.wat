@@ -0,0 +1,7 @@
+;; Exports nothing a request could be handled with.
+(component
+  (core module $guest
+    (func (export "handle")))
+  (core instance $guest (instantiate $guest))
+  (func $handle (canon lift (core func $guest "handle")))
+  (export "handle" (func $handle)))
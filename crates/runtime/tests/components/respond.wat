@@ -0,0 +1,38 @@
+;; Responds with status 403, the header `x-reply: denied` twice and no body.
+(component
+  (type $target (variant (case "address" string) (case "upstream" string)))
+  (type $response (record
+    (field "status-code" u16)
+    (field "headers" (list (tuple string (list u8))))
+    (field "body" (option (list u8)))))
+  (type $resolution (variant
+    (case "forward")
+    (case "forward-to" $target)
+    (case "respond" $response)))
+
+  (core module $guest
+    (memory (export "memory") 1)
+    (data (i32.const 100) "x-reply")
+    (data (i32.const 110) "denied")
+    ;; [("x-reply", "denied"), ("x-reply", "denied")]
+    (data (i32.const 200)
+      "\64\00\00\00\07\00\00\00\6e\00\00\00\06\00\00\00"
+      "\64\00\00\00\07\00\00\00\6e\00\00\00\06\00\00\00")
+    (func (export "handle") (result i32)
+      ;; respond(response { status-code: 403, headers, body: none })
+      (i32.store8 (i32.const 16) (i32.const 2))
+      (i32.store16 (i32.const 20) (i32.const 403))
+      (i32.store (i32.const 24) (i32.const 200))
+      (i32.store (i32.const 28) (i32.const 2))
+      (i32.store8 (i32.const 32) (i32.const 0))
+      (i32.const 16)))
+  (core instance $guest (instantiate $guest))
+
+  (func $handle (result $resolution)
+    (canon lift (core func $guest "handle") (memory (core memory $guest "memory"))))
+  (instance $proxy
+    (export "target" (type $target))
+    (export "response" (type $response))
+    (export "resolution" (type $resolution))
+    (export "handle" (func $handle)))
+  (export "wit:crossroads/proxy@0.2.0" (instance $proxy)))
@@ -0,0 +1,88 @@
+;; Forwards every request and marks the upstream response in `on-response`
+;; with the status 202, the header `x-hook: seen` and the URI of the request
+;; in `x-request-uri`.
+(component
+  (type $target (variant (case "address" string) (case "upstream" string)))
+  (type $response (record
+    (field "status-code" u16)
+    (field "headers" (list (tuple string (list u8))))
+    (field "body" (option (list u8)))))
+  (type $resolution (variant
+    (case "forward")
+    (case "forward-to" $target)
+    (case "respond" $response)))
+
+  (import "wit:crossroads/request@0.2.0" (instance $request
+    (export "uri" (func (result string)))))
+  (import "wit:crossroads/upstream-response@0.2.0" (instance $upstream-response
+    (export "set-status" (func (param "status" u16) (result (result (error string)))))
+    (export "set-header" (func
+      (param "name" string) (param "value" (list u8))
+      (result (result (error string)))))))
+
+  (core module $memory
+    (memory (export "memory") 1)
+    (global $next (mut i32) (i32.const 1024))
+    ;; Bump allocator, nothing is ever freed.
+    (func (export "realloc") (param i32 i32 i32 i32) (result i32)
+      (local $pointer i32)
+      (local.set $pointer
+        (i32.and
+          (i32.add (global.get $next) (i32.sub (local.get 2) (i32.const 1)))
+          (i32.sub (i32.const 0) (local.get 2))))
+      (global.set $next (i32.add (local.get $pointer) (local.get 3)))
+      (local.get $pointer)))
+  (core instance $memory (instantiate $memory))
+  (alias core export $memory "memory" (core memory $memory))
+  (alias core export $memory "realloc" (core func $realloc))
+
+  (core func $uri (canon lower (func $request "uri")
+    (memory $memory) (realloc $realloc)))
+  (core func $set-status (canon lower (func $upstream-response "set-status")
+    (memory $memory) (realloc $realloc)))
+  (core func $set-header (canon lower (func $upstream-response "set-header")
+    (memory $memory) (realloc $realloc)))
+
+  (core module $guest
+    (import "host" "memory" (memory 1))
+    (import "host" "uri" (func $uri (param i32)))
+    (import "host" "set-status" (func $set-status (param i32 i32)))
+    (import "host" "set-header" (func $set-header (param i32 i32 i32 i32 i32)))
+    (data (i32.const 100) "x-hook")
+    (data (i32.const 110) "seen")
+    (data (i32.const 120) "x-request-uri")
+    (func (export "handle") (result i32)
+      (i32.store8 (i32.const 16) (i32.const 0))
+      (i32.const 16))
+    (func (export "on-response") (result i32)
+      (call $set-status (i32.const 202) (i32.const 0))
+      (call $set-header (i32.const 100) (i32.const 6) (i32.const 110) (i32.const 4) (i32.const 0))
+      (call $uri (i32.const 32))
+      (call $set-header
+        (i32.const 120) (i32.const 13)
+        (i32.load (i32.const 32)) (i32.load (i32.const 36))
+        (i32.const 0))
+      ;; none, the upstream response is kept with the changes above
+      (i32.store8 (i32.const 16) (i32.const 0))
+      (i32.const 16)))
+  (core instance $guest (instantiate $guest
+    (with "host" (instance
+      (export "memory" (memory $memory))
+      (export "uri" (func $uri))
+      (export "set-status" (func $set-status))
+      (export "set-header" (func $set-header))))))
+
+  (func $handle (result $resolution)
+    (canon lift (core func $guest "handle") (memory $memory)))
+  (func $on-response (result (option $response))
+    (canon lift (core func $guest "on-response") (memory $memory)))
+  (instance $proxy
+    (export "target" (type $target))
+    (export "response" (type $response))
+    (export "resolution" (type $resolution))
+    (export "handle" (func $handle)))
+  (instance $response-hook
+    (export "response" (type $response))
+    (export "on-response" (func $on-response)))
+  (export "wit:crossroads/proxy@0.2.0" (instance $proxy))
+  (export "wit:crossroads/response-hook@0.2.0" (instance $response-hook)))
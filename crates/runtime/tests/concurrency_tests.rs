@@ -0,0 +1,49 @@
+mod common;
+
+use anyhow::Result;
+use rama::http::Body;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::task::JoinSet;
+
+use common::{MemoryStore, forwarded, request, runtime, runtime_with_store};
+use runtime::Error;
+
+const REQUESTS: u32 = 8;
+
+#[tokio::test]
+async fn guests_waiting_on_host_calls_share_a_thread() -> Result<()> {
+    let delay = Duration::from_millis(200);
+    let store = Arc::new(MemoryStore::slow(delay));
+    let runtime = runtime_with_store(&Default::default(), "slow_host", store)?;
+
+    let started = Instant::now();
+    let mut requests = JoinSet::new();
+    for _ in 0..REQUESTS {
+        let runtime = runtime.clone();
+        requests.spawn(async move { runtime.process(request(Body::empty())?).await });
+    }
+    while let Some(resolution) = requests.join_next().await {
+        forwarded(resolution??)?;
+    }
+    let elapsed = started.elapsed();
+    assert!(elapsed < delay * REQUESTS / 2, "took {:?}", elapsed);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn spinning_guest_yields_to_other_tasks() -> Result<()> {
+    let runtime = runtime(&Default::default(), "spin")?;
+
+    let started = Instant::now();
+    let (resolution, slept) = tokio::join!(runtime.process(request(Body::empty())?), async {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        started.elapsed()
+    });
+    let error = resolution.unwrap_err();
+    assert!(matches!(error, Error::Timeout), "{}", error);
+    assert!(slept < started.elapsed() / 2, "woke up after {:?}", slept);
+
+    Ok(())
+}
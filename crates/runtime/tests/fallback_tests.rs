@@ -0,0 +1,176 @@
+mod common;
+
+use anyhow::{Result, bail};
+use rama::http::Body;
+use rama::http::dep::http_body;
+use tokio::sync::broadcast::error::TryRecvError;
+
+use common::{Chunks, collect, component, forwarded, request, runtime};
+use runtime::configuration::{Configuration, fallback, rollback};
+use runtime::proxy::Proxy;
+use runtime::resolution::Resolution;
+use runtime::{Error, Runtime};
+
+#[test]
+fn fallback_is_read_as_map() -> Result<()> {
+    let configuration: Configuration = serde_yaml::from_str(
+        r#"
+        fallback:
+          response:
+            status_code: 503
+            body: Service temporarily unavailable
+        "#,
+    )?;
+    let Some(fallback::Configuration::Response(response)) = configuration.fallback else {
+        bail!("Fallback is not a response");
+    };
+    assert_eq!(response.status_code, 503);
+    assert_eq!(response.body, "Service temporarily unavailable");
+
+    let configuration: Configuration = serde_yaml::from_str(
+        r#"
+        fallback:
+          proxy:
+            tag: maintenance:v1.0.0
+        "#,
+    )?;
+    assert_eq!(configuration.fallback_tag(), Some("maintenance:v1.0.0"));
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn fallback_proxy_sees_body() -> Result<()> {
+    let runtime = with_fallback("spin")?;
+
+    let Resolution::Respond(response) = runtime.process(request("hello")?).await? else {
+        bail!("Fallback proxy did not respond");
+    };
+    assert_eq!(collect(response.into_body()).await?, b"hello");
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn body_of_unknown_size_is_not_buffered() -> Result<()> {
+    let runtime = with_fallback("forward")?;
+    let body = Body::new(Chunks::new(&["hello ", "world"]));
+
+    let forward = forwarded(runtime.process(request(body)?).await?)?;
+    let body = forward.request.into_body();
+    assert!(http_body::Body::size_hint(&body).upper().is_none());
+    assert_eq!(collect(body).await?, b"hello world");
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn body_of_unknown_size_gets_fallback() -> Result<()> {
+    let runtime = with_fallback("start_trap")?;
+    let body = Body::new(Chunks::new(&["hel", "lo"]));
+
+    let Resolution::Respond(response) = runtime.process(request(body)?).await? else {
+        bail!("Fallback proxy did not respond");
+    };
+    assert_eq!(collect(response.into_body()).await?, b"hello");
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn consumed_body_gets_no_fallback() -> Result<()> {
+    let runtime = with_fallback("read_chunk_trap")?;
+
+    let error = runtime.process(request("hello")?).await.unwrap_err();
+    assert!(matches!(error, Error::Failed(_)), "{}", error);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn traps_within_window_trigger_rollback() -> Result<()> {
+    let configuration = Configuration {
+        rollback: Some(rollback::Configuration {
+            max_traps: 3,
+            window_ms: 60_000,
+        }),
+        ..Default::default()
+    };
+    let runtime = runtime(&configuration, "forward")?;
+    runtime.set_proxy(&Proxy::new("trap".to_string(), component("start_trap")))?;
+    let mut rollbacks = runtime.subscribe_rollbacks();
+
+    for _ in 0..2 {
+        assert!(runtime.process(request("")?).await.is_err());
+    }
+    assert!(matches!(rollbacks.try_recv(), Err(TryRecvError::Empty)));
+    assert!(runtime.process(request("")?).await.is_err());
+    assert_eq!(rollbacks.try_recv()?.tag, "trap");
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn traps_of_forgotten_proxy_are_dropped() -> Result<()> {
+    let configuration = Configuration {
+        rollback: Some(rollback::Configuration {
+            max_traps: 2,
+            window_ms: 60_000,
+        }),
+        ..Default::default()
+    };
+    let runtime = runtime(&configuration, "forward")?;
+    let proxy = Proxy::new("trap".to_string(), component("start_trap"));
+    runtime.set_proxy(&proxy)?;
+    let mut rollbacks = runtime.subscribe_rollbacks();
+
+    assert!(runtime.process(request("")?).await.is_err());
+    runtime.forget_proxy(&proxy);
+    runtime.set_proxy(&proxy)?;
+    assert!(runtime.process(request("")?).await.is_err());
+    assert!(matches!(rollbacks.try_recv(), Err(TryRecvError::Empty)));
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn traps_of_fallback_proxy_are_recorded() -> Result<()> {
+    let configuration = Configuration {
+        fallback: Some(fallback::Configuration::Proxy(fallback::Proxy {
+            tag: "fallback".to_string(),
+        })),
+        rollback: Some(rollback::Configuration {
+            max_traps: 1,
+            window_ms: 60_000,
+        }),
+        ..Default::default()
+    };
+    let runtime = runtime(&configuration, "forward")?;
+    runtime.set_proxy(&Proxy::new("trap".to_string(), component("start_trap")))?;
+    runtime.set_fallback(Some(&Proxy::new(
+        "fallback".to_string(),
+        component("start_trap"),
+    )))?;
+    let mut rollbacks = runtime.subscribe_rollbacks();
+
+    assert!(runtime.process(request("")?).await.is_err());
+    assert_eq!(runtime.stats().failures, 2);
+    assert_eq!(rollbacks.try_recv()?.tag, "trap");
+    assert_eq!(rollbacks.try_recv()?.tag, "fallback");
+
+    Ok(())
+}
+
+/// Runtime serving `name` with the echoing `body` component as fallback
+/// proxy.
+fn with_fallback(name: &str) -> Result<Runtime> {
+    let configuration = Configuration {
+        fallback: Some(fallback::Configuration::Proxy(fallback::Proxy {
+            tag: "body".to_string(),
+        })),
+        ..Default::default()
+    };
+    let runtime = runtime(&configuration, name)?;
+    runtime.set_fallback(Some(&Proxy::new("body".to_string(), component("body"))))?;
+    Ok(runtime)
+}
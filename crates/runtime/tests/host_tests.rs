@@ -0,0 +1,212 @@
+mod common;
+
+use anyhow::Result;
+use http::header::{CONTENT_LENGTH, TRANSFER_ENCODING};
+use http::{HeaderValue, Method};
+use rama::http::Body;
+
+use common::{Chunks, collect, component, forwarded, request, runtime};
+use runtime::proxy::Proxy;
+use runtime::resolution::{Resolution, Target};
+
+#[tokio::test(flavor = "multi_thread")]
+async fn method_is_rewritten_to_extension_method() -> Result<()> {
+    let runtime = runtime(&Default::default(), "method")?;
+
+    let forward = forwarded(runtime.process(request(Body::empty())?).await?)?;
+    assert_eq!(forward.request.method().as_str(), "PURGE");
+    assert!(!forward.request.headers().contains_key("x-method"));
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn extension_method_is_read_verbatim() -> Result<()> {
+    let runtime = runtime(&Default::default(), "method")?;
+    let mut request = request(Body::empty())?;
+    *request.method_mut() = Method::from_bytes(b"PURGE")?;
+
+    let forward = forwarded(runtime.process(request).await?)?;
+    assert_eq!(forward.request.method(), Method::GET);
+    assert_eq!(forward.request.headers()["x-method"], "PURGE");
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn body_within_limit_is_read() -> Result<()> {
+    let runtime = runtime(&Default::default(), "body")?;
+
+    let resolution = runtime.process(request("short")?).await?;
+    let Resolution::Respond(response) = resolution else {
+        panic!("Expected the body to be echoed");
+    };
+    assert_eq!(collect(response.into_body()).await?, b"short");
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn chunked_body_over_limit_is_forwarded_intact() -> Result<()> {
+    let runtime = runtime(&Default::default(), "body")?;
+    let body = Body::new(Chunks::new(&["hello ", "world, ", "this is ", "chunked"]));
+
+    let forward = forwarded(runtime.process(request(body)?).await?)?;
+    assert_eq!(
+        collect(forward.request.into_body()).await?,
+        b"hello world, this is chunked"
+    );
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn response_carries_headers_of_the_component() -> Result<()> {
+    let runtime = runtime(&Default::default(), "respond")?;
+
+    let Resolution::Respond(response) = runtime.process(request(Body::empty())?).await? else {
+        panic!("Expected the component to respond");
+    };
+    assert_eq!(response.status(), 403);
+    let values = response
+        .headers()
+        .get_all("x-reply")
+        .iter()
+        .collect::<Vec<_>>();
+    assert_eq!(values, ["denied", "denied"]);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn partially_read_body_is_forwarded_with_its_remaining_length() -> Result<()> {
+    let runtime = runtime(&Default::default(), "read_chunk")?;
+    let mut request = request(Body::new(Chunks::new(&["hello ", "world"])))?;
+    request
+        .headers_mut()
+        .insert(CONTENT_LENGTH, HeaderValue::from_static("11"));
+
+    let forward = forwarded(runtime.process(request).await?)?;
+    assert_eq!(forward.request.headers()[CONTENT_LENGTH], "5");
+    assert_eq!(collect(forward.request.into_body()).await?, b"world");
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn partially_read_chunked_body_drops_its_transfer_encoding() -> Result<()> {
+    let runtime = runtime(&Default::default(), "read_chunk")?;
+    let mut request = request(Body::new(Chunks::new(&["hello ", "world"])))?;
+    request
+        .headers_mut()
+        .insert(TRANSFER_ENCODING, HeaderValue::from_static("chunked"));
+
+    let forward = forwarded(runtime.process(request).await?)?;
+    let headers = forward.request.headers();
+    assert!(!headers.contains_key(TRANSFER_ENCODING));
+    assert!(!headers.contains_key(CONTENT_LENGTH));
+    assert_eq!(collect(forward.request.into_body()).await?, b"world");
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn headers_keep_every_value_as_bytes() -> Result<()> {
+    let runtime = runtime(&Default::default(), "headers")?;
+    let mut request = request(Body::empty())?;
+    request
+        .headers_mut()
+        .append("x-in", HeaderValue::from_static("a"));
+    request
+        .headers_mut()
+        .append("x-in", HeaderValue::from_static("b"));
+
+    let forward = forwarded(runtime.process(request).await?)?;
+    let headers = forward.request.headers();
+    assert_eq!(headers.get_all("x-in").iter().count(), 2);
+    assert_eq!(headers["x-count"], "2");
+    assert_eq!(headers["x-raw"].as_bytes(), b"\xff\xfe");
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn target_is_picked_apart_from_uri() -> Result<()> {
+    let runtime = runtime(&Default::default(), "forward_to")?;
+
+    let forward = forwarded(runtime.process(request(Body::empty())?).await?)?;
+    assert_eq!(
+        forward.target,
+        Some(Target::Upstream("backend".to_string()))
+    );
+    assert_eq!(forward.request.uri(), "http://example.com/");
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn target_survives_later_plain_forward() -> Result<()> {
+    let runtime = runtime(&Default::default(), "forward")?;
+    runtime.set_chain(&[
+        Proxy::new("target".to_string(), component("forward_to")),
+        Proxy::new("plain".to_string(), component("forward")),
+    ])?;
+
+    let forward = forwarded(runtime.process(request(Body::empty())?).await?)?;
+    assert_eq!(
+        forward.target,
+        Some(Target::Upstream("backend".to_string()))
+    );
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn component_reads_configured_key() -> Result<()> {
+    let runtime = runtime(&Default::default(), "forward")?;
+    let mut proxy = Proxy::new("config".to_string(), component("config"));
+    proxy.configuration = Some(serde_json::json!({ "greeting": "hello" }));
+    runtime.set_proxy(&proxy)?;
+
+    let Resolution::Respond(response) = runtime.process(request(Body::empty())?).await? else {
+        panic!("Expected the component to respond");
+    };
+    assert_eq!(response.status(), 200);
+    assert_eq!(collect(response.into_body()).await?, b"hello");
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn component_reads_nothing_for_missing_key() -> Result<()> {
+    let runtime = runtime(&Default::default(), "forward")?;
+    runtime.set_proxy(&Proxy::new("config".to_string(), component("config")))?;
+
+    let Resolution::Respond(response) = runtime.process(request(Body::empty())?).await? else {
+        panic!("Expected the component to respond");
+    };
+    assert_eq!(response.status(), 200);
+    assert!(collect(response.into_body()).await?.is_empty());
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn legacy_component_runs_in_chain_with_current_one() -> Result<()> {
+    let runtime = runtime(&Default::default(), "forward")?;
+    runtime.set_chain(&[
+        Proxy::new("legacy".to_string(), component("legacy")),
+        Proxy::new("headers".to_string(), component("headers")),
+    ])?;
+    let mut request = request(Body::empty())?;
+    request
+        .headers_mut()
+        .append("x-in", HeaderValue::from_static("a"));
+
+    let forward = forwarded(runtime.process(request).await?)?;
+    let headers = forward.request.headers();
+    assert_eq!(headers["x-legacy"], "1");
+    assert_eq!(headers["x-count"], "1");
+
+    Ok(())
+}
@@ -0,0 +1,124 @@
+mod common;
+
+use anyhow::Result;
+use garde::Validate;
+use rama::http::Body;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use common::{MemoryStore, collect, forwarded, request, runtime, runtime_with_store};
+use runtime::Error;
+use runtime::configuration::{Configuration, limits, pooling, proxy};
+
+#[tokio::test(flavor = "multi_thread")]
+async fn endless_guest_times_out() -> Result<()> {
+    let runtime = runtime(&Default::default(), "spin")?;
+
+    let error = runtime.process(request(Body::empty())?).await.unwrap_err();
+    assert!(matches!(error, Error::Timeout), "{}", error);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn slow_host_call_does_not_count() -> Result<()> {
+    let store = Arc::new(MemoryStore::slow(Duration::from_millis(300)));
+    let runtime = runtime_with_store(&Default::default(), "slow_host", store)?;
+
+    let resolution = runtime.process(request(Body::empty())?).await?;
+    forwarded(resolution)?;
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn growing_memory_beyond_limit_fails() -> Result<()> {
+    let configuration = Configuration {
+        limits: limits::Configuration {
+            max_memory_bytes: 1024 * 1024,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let runtime = runtime(&configuration, "grow")?;
+
+    let error = runtime.process(request(Body::empty())?).await.unwrap_err();
+    assert!(matches!(error, Error::MemoryLimit(_)), "{}", error);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn growing_memory_within_limit_succeeds() -> Result<()> {
+    let runtime = runtime(&Default::default(), "grow")?;
+
+    let resolution = runtime.process(request(Body::empty())?).await?;
+    forwarded(resolution)?;
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn body_limit_of_guest_is_lowered_to_configured_one() -> Result<()> {
+    let configuration = Configuration {
+        limits: limits::Configuration {
+            max_body_bytes: 4,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let runtime = runtime(&configuration, "body")?;
+
+    // The component asks for up to 8 bytes and responds with bodies that fit.
+    let forward = forwarded(runtime.process(request("hello")?).await?)?;
+    assert_eq!(collect(forward.request.into_body()).await?, b"hello");
+
+    Ok(())
+}
+
+#[test]
+fn memory_limit_above_pooled_memory_is_rejected() {
+    let configuration = Configuration {
+        limits: limits::Configuration {
+            max_memory_bytes: 2 * 1024 * 1024,
+            ..Default::default()
+        },
+        pooling: Some(pooling::Configuration {
+            max_memory_size: 1024 * 1024,
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+
+    assert!(configuration.validate().is_err());
+}
+
+#[test]
+fn proxy_memory_limit_above_pooled_memory_is_rejected() {
+    let proxy = proxy::Configuration {
+        limits: Some(limits::Configuration {
+            max_memory_bytes: 128 * 1024 * 1024,
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+    let configuration = Configuration {
+        pooling: Some(Default::default()),
+        proxies: HashMap::from([("auth:v1".to_string(), proxy)]),
+        ..Default::default()
+    };
+
+    let error = configuration.validate().unwrap_err();
+    assert!(error.to_string().contains("auth:v1"), "{}", error);
+}
+
+#[test]
+fn memory_limits_within_pooled_memory_are_accepted() {
+    let configuration = Configuration {
+        pooling: Some(Default::default()),
+        ..Default::default()
+    };
+
+    assert!(configuration.validate().is_ok());
+}
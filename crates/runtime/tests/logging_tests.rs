@@ -0,0 +1,129 @@
+mod common;
+
+use anyhow::Result;
+use rama::http::Body;
+use std::fmt::Debug;
+use std::sync::{Arc, Mutex};
+use tracing::field::{Field, Visit};
+use tracing::{Event, Metadata, Subscriber, span};
+
+use common::{forwarded, request, runtime};
+use runtime::configuration::{Configuration, logging};
+
+// The guest runs on the thread of the test, so a thread local subscriber sees
+// its events.
+#[tokio::test]
+async fn guest_fields_are_recorded_as_event_fields() -> Result<()> {
+    let events = Events::default();
+    let _guard = tracing::subscriber::set_default(events.clone());
+    let runtime = runtime(&Default::default(), "log")?;
+
+    forwarded(runtime.process(request(Body::empty())?).await?)?;
+
+    let events = events.0.lock().unwrap();
+    let [event] = events.as_slice() else {
+        panic!("Expected one guest event, got {:?}", events);
+    };
+    assert_eq!(event.level, tracing::Level::WARN);
+    assert_eq!(event.field("message"), Some("denied"));
+    assert_eq!(event.field("fields.user"), Some("alice"));
+    assert_eq!(event.field("fields.attempt"), Some("2"));
+    assert!(event.field("tag").is_some());
+    assert!(event.field("request_id").is_some());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn guest_event_below_log_level_is_dropped() -> Result<()> {
+    let events = Events::default();
+    let _guard = tracing::subscriber::set_default(events.clone());
+    let configuration = Configuration {
+        log_level: logging::Level::Error,
+        ..Default::default()
+    };
+    let runtime = runtime(&configuration, "log")?;
+
+    forwarded(runtime.process(request(Body::empty())?).await?)?;
+
+    assert!(events.0.lock().unwrap().is_empty());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn generated_request_id_is_not_forwarded() -> Result<()> {
+    let events = Events::default();
+    let _guard = tracing::subscriber::set_default(events.clone());
+    let runtime = runtime(&Default::default(), "log")?;
+
+    let forward = forwarded(runtime.process(request(Body::empty())?).await?)?;
+
+    assert!(!forward.request.headers().contains_key("x-request-id"));
+    let events = events.0.lock().unwrap();
+    assert!(
+        events[0]
+            .field("request_id")
+            .is_some_and(|id| !id.is_empty())
+    );
+
+    Ok(())
+}
+
+#[derive(Debug)]
+struct Captured {
+    level: tracing::Level,
+    fields: Vec<(String, String)>,
+}
+
+impl Captured {
+    fn field(&self, name: &str) -> Option<&str> {
+        self.fields
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+impl Visit for Captured {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.fields
+            .push((field.name().to_string(), value.to_string()));
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        self.fields
+            .push((field.name().to_string(), format!("{:?}", value)));
+    }
+}
+
+/// Subscriber keeping the events of the `guest` target.
+#[derive(Clone, Default)]
+struct Events(Arc<Mutex<Vec<Captured>>>);
+
+impl Subscriber for Events {
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        metadata.target() == "guest"
+    }
+
+    fn new_span(&self, _: &span::Attributes<'_>) -> span::Id {
+        span::Id::from_u64(1)
+    }
+
+    fn record(&self, _: &span::Id, _: &span::Record<'_>) {}
+
+    fn record_follows_from(&self, _: &span::Id, _: &span::Id) {}
+
+    fn event(&self, event: &Event<'_>) {
+        let mut captured = Captured {
+            level: *event.metadata().level(),
+            fields: Vec::new(),
+        };
+        event.record(&mut captured);
+        self.0.lock().unwrap().push(captured);
+    }
+
+    fn enter(&self, _: &span::Id) {}
+
+    fn exit(&self, _: &span::Id) {}
+}
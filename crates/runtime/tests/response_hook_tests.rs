@@ -0,0 +1,78 @@
+mod common;
+
+use anyhow::Result;
+use rama::http::{Body, Response};
+
+use common::{forwarded, request, runtime};
+use runtime::configuration::{Configuration, pooling};
+
+#[tokio::test(flavor = "multi_thread")]
+async fn hook_changes_upstream_response() -> Result<()> {
+    let runtime = runtime(&Default::default(), "response_hook")?;
+
+    let forward = forwarded(runtime.process(request(Body::empty())?).await?)?;
+    let hook = forward
+        .response_hook
+        .expect("Component exports a response hook");
+    let response = hook.process(Response::new(Body::empty())).await?;
+    assert_eq!(response.status(), 202);
+    assert_eq!(response.headers()["x-hook"], "seen");
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn hook_reads_head_of_forwarded_request() -> Result<()> {
+    let runtime = runtime(&Default::default(), "response_hook")?;
+    let mut request = request(Body::empty())?;
+    *request.uri_mut() = "http://example.com/items?page=2".parse()?;
+
+    let forward = forwarded(runtime.process(request).await?)?;
+    let hook = forward
+        .response_hook
+        .expect("Component exports a response hook");
+    let response = hook.process(Response::new(Body::empty())).await?;
+    assert_eq!(
+        response.headers()["x-request-uri"],
+        "http://example.com/items?page=2"
+    );
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn component_without_hook_has_none() -> Result<()> {
+    let runtime = runtime(&Default::default(), "forward")?;
+
+    let forward = forwarded(runtime.process(request(Body::empty())?).await?)?;
+    assert!(forward.response_hook.is_none());
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn pending_hook_does_not_hold_an_instance() -> Result<()> {
+    let configuration = Configuration {
+        pooling: Some(pooling::Configuration {
+            total_component_instances: 1,
+            total_core_instances: 4,
+            total_memories: 2,
+            total_tables: 2,
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+    let runtime = runtime(&configuration, "response_hook")?;
+
+    let first = forwarded(runtime.process(request(Body::empty())?).await?)?;
+    let second = forwarded(runtime.process(request(Body::empty())?).await?)?;
+    for forward in [first, second] {
+        let hook = forward
+            .response_hook
+            .expect("Component exports a response hook");
+        let response = hook.process(Response::new(Body::empty())).await?;
+        assert_eq!(response.status(), 202);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,94 @@
+mod common;
+
+use anyhow::Result;
+use rama::http::Body;
+
+use common::{component, request, runtime};
+
+#[test]
+fn component_of_current_world_is_valid() -> Result<()> {
+    let runtime = runtime(&Default::default(), "forward")?;
+
+    assert!(runtime.validate(&component("response_hook")).is_ok());
+
+    Ok(())
+}
+
+#[test]
+fn every_unknown_import_is_unsatisfied() -> Result<()> {
+    let runtime = runtime(&Default::default(), "forward")?;
+
+    let incompatible = runtime.validate(&component("unknown_import")).unwrap_err();
+    assert_eq!(
+        incompatible.unsatisfied_imports,
+        [
+            "wit:crossroads/unknown@0.2.0",
+            "wit:crossroads/missing@0.2.0"
+        ]
+    );
+    assert!(incompatible.missing_exports.is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn import_of_compatible_wasi_version_is_satisfied() -> Result<()> {
+    let runtime = runtime(&Default::default(), "forward")?;
+
+    assert!(runtime.validate(&component("environment")).is_ok());
+
+    Ok(())
+}
+
+#[test]
+fn component_of_legacy_world_is_valid() -> Result<()> {
+    let runtime = runtime(&Default::default(), "forward")?;
+
+    assert!(runtime.validate(&component("legacy")).is_ok());
+
+    Ok(())
+}
+
+#[test]
+fn imports_of_another_world_version_are_unsatisfied() -> Result<()> {
+    let runtime = runtime(&Default::default(), "forward")?;
+
+    let incompatible = runtime.validate(&component("mixed_versions")).unwrap_err();
+    assert_eq!(
+        incompatible.unsatisfied_imports,
+        ["wit:crossroads/request@0.1.0", "wit:crossroads/kv@0.1.0"]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn missing_proxy_export_is_reported() -> Result<()> {
+    let runtime = runtime(&Default::default(), "forward")?;
+
+    let incompatible = runtime.validate(&component("no_export")).unwrap_err();
+    assert_eq!(incompatible.missing_exports, ["wit:crossroads/proxy@0.2.0"]);
+
+    Ok(())
+}
+
+#[test]
+fn invalid_binary_is_reported() -> Result<()> {
+    let runtime = runtime(&Default::default(), "forward")?;
+
+    let incompatible = runtime.validate(b"\0asm").unwrap_err();
+    assert_eq!(incompatible.errors.len(), 1);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn validation_does_not_instantiate() -> Result<()> {
+    let forward = runtime(&Default::default(), "forward")?;
+    assert!(forward.validate(&component("start_trap")).is_ok());
+
+    let start_trap = runtime(&Default::default(), "start_trap")?;
+    assert!(start_trap.process(request(Body::empty())?).await.is_err());
+
+    Ok(())
+}
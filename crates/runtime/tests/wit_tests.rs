@@ -0,0 +1,77 @@
+use anyhow::{Context, Result};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Digests of every published `wit:crossroads` version. Components built
+/// against a version must keep linking, so a changed interface needs a new
+/// package version rather than a new digest.
+const PUBLISHED: &[(&str, &str)] = &[
+    (
+        "0.1.0",
+        "796b33a5891f4e702a55b58f39af3f05e95d602ef8779c868079e0057bbe16e5",
+    ),
+    (
+        "0.2.0",
+        "da273feed0dcbe580dba9e4210e9ca3b7e00d46f4a170e970c6e2d00039e0bbc",
+    ),
+];
+
+#[test]
+fn current_package_is_unchanged_or_bumped() -> Result<()> {
+    assert_published(&manifest_dir().join("../proxy/wit"))
+}
+
+#[test]
+fn legacy_packages_are_unchanged() -> Result<()> {
+    for entry in fs::read_dir(manifest_dir().join("wit"))? {
+        assert_published(&entry?.path())?;
+    }
+    Ok(())
+}
+
+fn assert_published(directory: &Path) -> Result<()> {
+    let version = version(directory)?;
+    let digest = digest(directory)?;
+    let published = PUBLISHED
+        .iter()
+        .find(|(published, _)| *published == version)
+        .map(|(_, digest)| *digest);
+    assert_eq!(
+        published,
+        Some(digest.as_str()),
+        "wit:crossroads@{} in {} differs from the published package, bump the \
+         package version and record its digest",
+        version,
+        directory.display()
+    );
+    Ok(())
+}
+
+fn version(directory: &Path) -> Result<String> {
+    let world = fs::read_to_string(directory.join("world.wit"))?;
+    world
+        .lines()
+        .find_map(|line| line.trim().strip_prefix("package wit:crossroads@"))
+        .and_then(|version| version.strip_suffix(';'))
+        .map(str::to_string)
+        .with_context(|| format!("{} declares no package", directory.display()))
+}
+
+fn digest(directory: &Path) -> Result<String> {
+    let mut files = fs::read_dir(directory)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+    files.retain(|file| file.extension().is_some_and(|extension| extension == "wit"));
+    files.sort();
+    let mut hasher = Sha256::new();
+    for file in files {
+        hasher.update(file.file_name().unwrap_or_default().as_encoded_bytes());
+        hasher.update(fs::read(&file)?);
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+fn manifest_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+}
@@ -10,6 +10,9 @@ pub struct Configuration {
     #[garde(dive)]
     #[serde(default)]
     pub gateway: gateway::configuration::Configuration,
+    #[garde(dive)]
+    #[serde(default)]
+    pub runtime: runtime::configuration::Configuration,
 }
 
 impl Configuration {
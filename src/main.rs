@@ -5,13 +5,19 @@ use anyhow::Result;
 
 #[tokio::main]
 async fn main() -> Result<()> {
+    // Without `RUST_LOG` guest events are limited by `log_level` alone.
+    let filter = tracing_subscriber::EnvFilter::try_from_default_env()
+        .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new("info,guest=trace"));
+    tracing_subscriber::fmt().with_env_filter(filter).init();
     let configuration = cli::evaluate()?;
 
-    let wasm_bytes = include_bytes!("../target/wasm32-wasip2/release/proxy.wasm");
-    let runtime = runtime::Runtime::new(wasm_bytes)?;
-
     let gateway = gateway::Gateway::new(&configuration.gateway)?;
-    let api = api::API::new(&configuration.api).await?;
+    let api = api::API::new(&configuration.api)
+        .await?
+        .merge(gateway.admin_routes());
+
+    let wasm_bytes = include_bytes!("../target/wasm32-wasip2/release/proxy.wasm");
+    let runtime = runtime::Runtime::new(&configuration.runtime, api.key_value_store(), wasm_bytes)?;
 
     let mut set = tokio::task::JoinSet::new();
     let gateway_runtime = runtime.clone();